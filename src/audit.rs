@@ -0,0 +1,227 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serenity::{
+    builder::CreateEmbed,
+    http::Http,
+    model::{
+        application::{
+            command::CommandOptionType,
+            interaction::application_command::{
+                ApplicationCommandInteraction, CommandDataOption, CommandDataOptionValue,
+            },
+        },
+        id::UserId,
+        mention::Mention,
+        Timestamp,
+    },
+};
+
+use crate::store::{AuditEntry, AuditRecord};
+
+/// How a privileged command ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Succeeded,
+    /// The options given were invalid, nothing was done.
+    Rejected,
+    Failed {
+        reference: String,
+    },
+}
+
+impl Outcome {
+    /// Short lowercase name, used as a metric label.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Succeeded => "succeeded",
+            Self::Rejected => "rejected",
+            Self::Failed { .. } => "failed",
+        }
+    }
+}
+
+impl std::fmt::Display for Outcome {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Succeeded => fmt.write_str("Succeeded"),
+            Self::Rejected => fmt.write_str("Rejected"),
+            Self::Failed { reference } => write!(fmt, "Failed (reference `{}`)", reference),
+        }
+    }
+}
+
+/// What the linking site knows about a member at one point in time.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinkState {
+    pub linked: bool,
+    pub steam_id: Option<u64>,
+    pub gmodstore_id: Option<String>,
+}
+
+impl LinkState {
+    /// Looks the user up, `None` when the lookup itself failed.
+    pub async fn fetch(handler: &crate::Handler, user: UserId) -> Option<Self> {
+        match handler.http.link_client.get_user_by_discord(user.0).await {
+            Ok(Some(linked)) => Some(Self {
+                linked: true,
+                steam_id: Some(linked.steam_id),
+                gmodstore_id: linked.gmod_store_id,
+            }),
+            Ok(None) => Some(Self {
+                linked: false,
+                steam_id: None,
+                gmodstore_id: None,
+            }),
+            Err(e) => {
+                warn!("Failed to snapshot link state for audit log: {:#?}", e);
+                None
+            }
+        }
+    }
+
+    fn describe(state: Option<&str>) -> String {
+        let state = state.and_then(|state| serde_json::from_str::<Self>(state).ok());
+        match state {
+            None => "Unknown".to_string(),
+            Some(state) if !state.linked => "Not linked".to_string(),
+            Some(state) => format!(
+                "Linked\nSteam: `{}`\nGmodStore: `{}`",
+                state
+                    .steam_id
+                    .map_or_else(|| "None".to_string(), |id| id.to_string()),
+                state.gmodstore_id.as_deref().unwrap_or("None")
+            ),
+        }
+    }
+}
+
+/// The command name plus any subcommand, e.g. `coupon-admin create`.
+pub fn action(command: &ApplicationCommandInteraction) -> String {
+    let subcommand = command
+        .data
+        .options
+        .iter()
+        .find(|option| option.kind == CommandOptionType::SubCommand);
+
+    match subcommand {
+        Some(subcommand) => format!("{} {}", command.data.name, subcommand.name),
+        None => command.data.name.clone(),
+    }
+}
+
+/// The first user option of the command, including those of a subcommand.
+pub fn target(command: &ApplicationCommandInteraction) -> Option<UserId> {
+    fn find(options: &[CommandDataOption]) -> Option<UserId> {
+        options.iter().find_map(|option| match &option.resolved {
+            Some(CommandDataOptionValue::User(user, _)) => Some(user.id),
+            _ => find(&option.options),
+        })
+    }
+
+    find(&command.data.options)
+}
+
+/// Stores an audit entry and posts it to the staff log channel.
+///
+/// Failures are logged rather than returned, the command has already run.
+pub async fn record(discord: &Http, handler: &crate::Handler, entry: AuditEntry) {
+    let id = match handler.store.record_audit(&entry) {
+        Ok(id) => Some(id),
+        Err(e) => {
+            error!("{:#?}", e);
+            None
+        }
+    };
+
+    let Some(channel) = handler.config.audit.channel else {
+        return;
+    };
+
+    let result = channel
+        .send_message(discord, |message| message.set_embed(embed(&entry, id)))
+        .await;
+
+    if let Err(e) = result {
+        error!("Failed to post audit entry: {:#?}", e);
+    }
+}
+
+fn embed(entry: &AuditEntry, id: Option<i64>) -> CreateEmbed {
+    let succeeded = entry.outcome == Outcome::Succeeded.to_string();
+
+    let mut embed = CreateEmbed::default();
+    embed
+        .title(format!("/{}", entry.action))
+        .field("Actor", Mention::User(UserId(entry.actor)), true)
+        .field(
+            "Target",
+            entry.target.map_or_else(
+                || "None".to_string(),
+                |id| Mention::User(UserId(id)).to_string(),
+            ),
+            true,
+        )
+        .field("Outcome", &entry.outcome, true)
+        .timestamp(
+            Timestamp::from_unix_timestamp(entry.created_at.timestamp())
+                .unwrap_or_else(|_| Timestamp::now()),
+        )
+        .colour(serenity::utils::Colour::from(if succeeded {
+            0x57F287
+        } else {
+            0xED4245
+        }));
+
+    if entry.target.is_some() {
+        embed
+            .field("Before", LinkState::describe(entry.before.as_deref()), true)
+            .field("After", LinkState::describe(entry.after.as_deref()), true);
+    }
+
+    if let Some(id) = id {
+        embed.footer(|footer| footer.text(format!("Audit #{}", id)));
+    }
+
+    embed
+}
+
+/// A one line summary, for listing several entries at once.
+pub fn summarise(record: &AuditRecord) -> String {
+    let entry = &record.entry;
+    let target = entry
+        .target
+        .map(|id| format!(" on {}", Mention::User(UserId(id))))
+        .unwrap_or_default();
+
+    format!(
+        "`#{}` <t:{}:R> {} ran `/{}`{}: {}",
+        record.id,
+        entry.created_at.timestamp(),
+        Mention::User(UserId(entry.actor)),
+        entry.action,
+        target,
+        entry.outcome
+    )
+}
+
+/// Builds the entry for a command run, from link state snapshots taken around it.
+pub fn entry(
+    command: &ApplicationCommandInteraction,
+    target: Option<UserId>,
+    before: Option<LinkState>,
+    after: Option<LinkState>,
+    outcome: &Outcome,
+) -> AuditEntry {
+    let serialise =
+        |state: Option<LinkState>| state.and_then(|state| serde_json::to_string(&state).ok());
+
+    AuditEntry {
+        created_at: Utc::now(),
+        actor: command.user.id.0,
+        target: target.map(|target| target.0),
+        action: action(command),
+        outcome: outcome.to_string(),
+        before: serialise(before),
+        after: serialise(after),
+    }
+}
@@ -0,0 +1,58 @@
+use chrono::{DateTime, Utc};
+
+use crate::{
+    config::{CampaignConfig, Config},
+    http::ApiPurchaseObject,
+};
+
+/// Campaigns that can be claimed right now, in config order.
+pub fn running(config: &Config, now: DateTime<Utc>) -> impl Iterator<Item = &CampaignConfig> {
+    config
+        .campaigns
+        .iter()
+        .filter(move |campaign| campaign.is_running(now))
+}
+
+pub fn find<'a>(config: &'a Config, key: &str) -> Option<&'a CampaignConfig> {
+    config
+        .campaigns
+        .iter()
+        .find(|campaign| campaign.key.eq_ignore_ascii_case(key))
+}
+
+/// Checks a user's purchases against a campaign's eligibility rules.
+///
+/// The error explains, in a sentence shown to the user, why they don't qualify.
+pub fn check(
+    config: &Config,
+    campaign: &CampaignConfig,
+    purchases: &ApiPurchaseObject,
+) -> Result<(), String> {
+    let label = |key: &String| {
+        config
+            .product(key)
+            .map_or_else(|| key.clone(), |product| product.label())
+    };
+    let rules = &campaign.eligibility;
+
+    if let Some(owned) = rules.excludes.iter().find(|key| purchases.owns(key)) {
+        return Err(format!("You already own {}.", label(owned)));
+    }
+
+    let missing: Vec<_> = rules
+        .requires_all
+        .iter()
+        .filter(|key| !purchases.owns(key))
+        .map(label)
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!("You must own {}.", missing.join(" and ")));
+    }
+
+    if !rules.requires_any.is_empty() && !rules.requires_any.iter().any(|key| purchases.owns(key)) {
+        let options: Vec<_> = rules.requires_any.iter().map(label).collect();
+        return Err(format!("You must own one of {}.", options.join(", ")));
+    }
+
+    Ok(())
+}
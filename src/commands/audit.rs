@@ -0,0 +1,106 @@
+use super::{CommandOptions, CommandResponse, CommandRuntimeError};
+use crate::store::AuditQuery;
+use async_trait::async_trait;
+use error_stack::{Result, ResultExt};
+use serenity::{
+    builder::{CreateApplicationCommand, CreateEmbed},
+    client::Context,
+    model::{
+        application::{
+            command::CommandOptionType,
+            interaction::application_command::ApplicationCommandInteraction,
+        },
+        mention::Mention,
+        permissions::Permissions,
+    },
+};
+
+const DEFAULT_LIMIT: i64 = 10;
+const MAX_LIMIT: i64 = 25;
+
+pub struct AuditCommand;
+
+#[async_trait]
+impl super::Command for AuditCommand {
+    async fn execute(
+        handler: &crate::Handler,
+        command: &ApplicationCommandInteraction,
+        _ctx: Context,
+    ) -> Result<CommandResponse, CommandRuntimeError> {
+        let options = CommandOptions::new(command);
+        let actor = options
+            .optional_user("actor")
+            .change_context(CommandRuntimeError)?;
+        let target = options
+            .optional_user("target")
+            .change_context(CommandRuntimeError)?;
+        let limit = options
+            .optional_integer("limit")
+            .change_context(CommandRuntimeError)?
+            .unwrap_or(DEFAULT_LIMIT)
+            .clamp(1, MAX_LIMIT);
+
+        let records = handler
+            .store
+            .audit_entries(&AuditQuery {
+                actor: actor.map(|actor| actor.id.0),
+                target: target.map(|target| target.id.0),
+                limit: limit as u32,
+            })
+            .change_context(CommandRuntimeError)?;
+
+        let mut filters = Vec::new();
+        if let Some(actor) = actor {
+            filters.push(format!("by {}", Mention::User(actor.id)));
+        }
+        if let Some(target) = target {
+            filters.push(format!("on {}", Mention::User(target.id)));
+        }
+
+        let mut embed = CreateEmbed::default();
+        embed
+            .title("Audit Log")
+            .colour(serenity::utils::Colour::from(0xBF8AE0));
+
+        let lines: Vec<_> = records.iter().map(crate::audit::summarise).collect();
+        let description = match (lines.is_empty(), filters.is_empty()) {
+            (true, _) => "No matching actions have been recorded.".to_string(),
+            (false, true) => lines.join("\n"),
+            (false, false) => format!("Actions {}\n\n{}", filters.join(" "), lines.join("\n")),
+        };
+        embed.description(description);
+
+        Ok(embed.into())
+    }
+
+    fn name() -> &'static str {
+        "audit"
+    }
+
+    fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+        command
+            .description("Show recent staff actions")
+            .create_option(|option| {
+                option
+                    .name("actor")
+                    .description("Only actions taken by this member")
+                    .kind(CommandOptionType::User)
+            })
+            .create_option(|option| {
+                option
+                    .name("target")
+                    .description("Only actions taken on this member")
+                    .kind(CommandOptionType::User)
+            })
+            .create_option(|option| {
+                option
+                    .name("limit")
+                    .description("How many actions to show, 10 by default")
+                    .kind(CommandOptionType::Integer)
+                    .min_int_value(1)
+                    .max_int_value(MAX_LIMIT)
+            })
+            .dm_permission(false)
+            .default_member_permissions(Permissions::MODERATE_MEMBERS)
+    }
+}
@@ -1,25 +1,171 @@
-use super::CommandRuntimeError;
-use crate::http::CouponBuilder;
+use super::{ArgumentError, CommandOptions, CommandResponse, CommandRuntimeError};
+use crate::{config::CampaignConfig, http::CouponBuilder, store::IssuedCoupon};
 use async_trait::async_trait;
-use error_stack::{IntoReport, Result, ResultExt};
+use chrono::{DateTime, Days, Utc};
+use error_stack::{Report, Result, ResultExt};
 use serenity::{
     builder::CreateApplicationCommand,
     client::Context,
-    model::application::interaction::{
-        application_command::ApplicationCommandInteraction, InteractionResponseType,
+    model::{
+        application::{
+            command::CommandOptionType,
+            interaction::application_command::ApplicationCommandInteraction,
+        },
+        id::UserId,
     },
 };
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
 
 pub struct CouponCommand;
 
+/// Coupons claimed before campaigns existed have a bare cuid for a code, with no
+/// prefix and no ledger entry. Prefixed codes always contain a `-`.
+fn is_legacy_code(code: &str) -> bool {
+    code.len() == usize::from(cuid2::DEFAULT_LENGTH) && cuid2::is_cuid2(code)
+}
+
+/// Makes each user's claims run one at a time, so two quick `/coupon`s can't both
+/// pass the limit check before either is recorded.
+#[derive(Default)]
+pub struct ClaimLocks {
+    users: Mutex<HashMap<u64, Arc<tokio::sync::Mutex<()>>>>,
+}
+
+impl ClaimLocks {
+    async fn lock(&self, discord_id: UserId) -> tokio::sync::OwnedMutexGuard<()> {
+        let lock = {
+            let mut users = self
+                .users
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            // Nobody else holds or waits on these, so they can go.
+            users.retain(|_, lock| Arc::strong_count(lock) > 1);
+            users.entry(discord_id.0).or_default().clone()
+        };
+
+        lock.lock_owned().await
+    }
+}
+
+impl CouponCommand {
+    /// Hands out the user's coupon for a campaign, reusing an active one if they have it.
+    async fn claim(
+        handler: &crate::Handler,
+        campaign: &CampaignConfig,
+        discord_id: UserId,
+        gmod_store_id: &str,
+    ) -> Result<String, CommandRuntimeError> {
+        let product = handler.config.product(&campaign.product).ok_or_else(|| {
+            Report::new(CommandRuntimeError)
+                .attach_printable(format!("Campaign {} has no product", campaign.key))
+        })?;
+        let Some(product_id) = &product.gmodstore_id else {
+            return Err(Report::new(CommandRuntimeError).attach_printable(format!(
+                "{} has no GmodStore product ID in the product catalog",
+                product.key
+            )));
+        };
+
+        let _claiming = handler.coupon_claims.lock(discord_id).await;
+
+        let prefix = format!("{}-", campaign.code_prefix());
+        let claimed: Vec<_> = handler
+            .http
+            .gmod_store_client
+            .get_product_coupons(product_id, Some(gmod_store_id))
+            .await
+            .change_context(CommandRuntimeError)?
+            .into_iter()
+            .filter(|coupon| coupon.code.starts_with(&prefix) || is_legacy_code(&coupon.code))
+            .collect();
+
+        let now = Utc::now();
+        let active = claimed.iter().find(|coupon| {
+            DateTime::parse_from_rfc3339(&coupon.expires_at).is_ok_and(|expiry| expiry > now)
+        });
+
+        if let Some(coupon) = active {
+            return Ok(format!(
+                "You already have a valid coupon code, use code `{}`",
+                coupon.code
+            ));
+        }
+
+        // Counted from the ledger, the listing only has coupons that still exist on GmodStore.
+        // Legacy coupons were never recorded there, so they are counted from the listing.
+        let legacy = claimed
+            .iter()
+            .filter(|coupon| is_legacy_code(&coupon.code))
+            .count() as u64;
+        let issued_before = handler
+            .store
+            .coupons_claimed(discord_id.0, &campaign.key)
+            .change_context(CommandRuntimeError)?;
+        if issued_before + legacy >= u64::from(campaign.per_user_limit) {
+            return Ok(format!("You have already claimed {}.", campaign.name));
+        }
+
+        let expiry = now + Days::new(campaign.lifetime_days);
+        let coupon_builder = CouponBuilder::new(
+            format!("{}{}", prefix, cuid2::cuid()),
+            campaign.percent,
+            campaign.max_uses,
+            Some(gmod_store_id.to_string()),
+        )
+        .and_then(|builder| builder.expires_at(expiry))
+        .change_context(CommandRuntimeError)?;
+
+        let coupon = handler
+            .http
+            .gmod_store_client
+            .create_coupon(product_id, coupon_builder)
+            .await
+            .change_context(CommandRuntimeError)?;
+
+        crate::metrics::metrics()
+            .coupons_issued
+            .with_label_values(&[&campaign.key])
+            .inc();
+
+        let issued = IssuedCoupon {
+            issued_at: now,
+            discord_id: discord_id.0,
+            gmodstore_id: gmod_store_id.to_string(),
+            campaign: campaign.key.clone(),
+            product_id: product_id.clone(),
+            coupon_id: coupon.id.clone(),
+            code: coupon.code.clone(),
+            expires_at: expiry,
+        };
+        // The coupon exists either way, so a ledger failure shouldn't hide it from the user.
+        if let Err(e) = handler.store.record_coupon(&issued) {
+            error!("{:#?}", e);
+        }
+
+        Ok(format!(
+            "Use code `{}` for {}% off {}, it expires <t:{}:R>.",
+            coupon.code,
+            campaign.percent,
+            product.label(),
+            expiry.timestamp()
+        ))
+    }
+}
+
 #[async_trait]
 impl super::Command for CouponCommand {
     async fn execute(
         handler: &crate::Handler,
-        command: &mut ApplicationCommandInteraction,
-        ctx: Context,
-    ) -> Result<(), CommandRuntimeError> {
+        command: &ApplicationCommandInteraction,
+        _ctx: Context,
+    ) -> Result<CommandResponse, CommandRuntimeError> {
         let discord_user = &command.user;
+        let chosen = CommandOptions::new(command)
+            .optional_string("campaign")
+            .change_context(CommandRuntimeError)?;
 
         let api_user = handler
             .http
@@ -30,10 +176,11 @@ impl super::Command for CouponCommand {
 
         let user = match api_user {
             Some(user) => user,
-            None => {
-                respond(command, &ctx, "You are not linked").await?;
-                return Ok(());
-            }
+            None => return Ok("You are not linked".into()),
+        };
+
+        let Some(gmod_store_id) = user.gmod_store_id.clone() else {
+            return Ok("You must link your GmodStore account to receive coupons.".into());
         };
 
         let purchases = user
@@ -41,83 +188,75 @@ impl super::Command for CouponCommand {
             .await
             .change_context(CommandRuntimeError)?;
 
-        if purchases.lsac {
-            respond(command, &ctx, "You already own LSAC!").await?;
-            return Ok(());
-        } else if !purchases.swift_ac {
-            respond(
-                command,
-                &ctx,
-                "You must own SwiftAC to get coupon for LSAC!",
-            )
-            .await?;
-            return Ok(());
-        }
+        let now = Utc::now();
 
-        let coupons = handler
-            .http
-            .gmod_store_client
-            .get_coupons_by_user(&user, "6c5e862b-3dcf-4769-aa6b-8a001937c56b")
-            .await
-            .change_context(CommandRuntimeError)?;
+        if let Some(key) = chosen {
+            let campaign = crate::campaigns::find(&handler.config, key)
+                .filter(|campaign| campaign.is_running(now))
+                .ok_or_else(|| {
+                    Report::new(ArgumentError::Invalid {
+                        name: "campaign".to_string(),
+                        reason: format!("there is no running campaign called `{}`", key),
+                    })
+                    .change_context(CommandRuntimeError)
+                })?;
 
-        if let Some(coupons) = coupons {
-            respond(
-                command,
-                &ctx,
-                format!(
-                    "You already have a valid coupon code, use code `{}`",
-                    coupons[0].code
-                )
-                .as_str(),
-            )
-            .await?;
-            return Ok(());
+            return Ok(
+                match crate::campaigns::check(&handler.config, campaign, &purchases) {
+                    Ok(()) => {
+                        Self::claim(handler, campaign, discord_user.id, &gmod_store_id).await?
+                    }
+                    Err(reason) => reason,
+                }
+                .into(),
+            );
         }
 
-        let coupon_code = cuid2::cuid();
+        let eligible: Vec<_> = crate::campaigns::running(&handler.config, now)
+            .filter(|campaign| {
+                crate::campaigns::check(&handler.config, campaign, &purchases).is_ok()
+            })
+            .collect();
 
-        let coupon_builder = CouponBuilder::new(coupon_code, 25, 1, user.gmod_store_id.clone())
-            .change_context(CommandRuntimeError)?;
+        Ok(match eligible.as_slice() {
+            [] => "There are no coupons available to you right now.".into(),
+            [campaign] => Self::claim(handler, campaign, discord_user.id, &gmod_store_id)
+                .await?
+                .into(),
+            campaigns => {
+                let lines: Vec<_> = campaigns
+                    .iter()
+                    .map(|campaign| {
+                        format!(
+                            "- **{}**: `/coupon campaign:{}`",
+                            campaign.name, campaign.key
+                        )
+                    })
+                    .collect();
+                format!(
+                    "You qualify for several offers, pick one:\n{}",
+                    lines.join("\n")
+                )
+                .into()
+            }
+        })
+    }
 
-        let coupon = handler
-            .http
-            .gmod_store_client
-            .create_coupon("6c5e862b-3dcf-4769-aa6b-8a001937c56b", coupon_builder)
-            .await
-            .change_context(CommandRuntimeError)?;
+    const DEFER: bool = true;
 
-        respond(
-            command,
-            &ctx,
-            format!("Use code: `{}`, it expires in 7 days.", coupon.code).as_str(),
-        )
-        .await?;
-        Ok(())
+    fn name() -> &'static str {
+        "coupon"
     }
 
     fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
         command
-            .name("coupon")
-            .description("Generate a coupon for LSAC.")
+            .description("Claim a coupon from a running offer.")
+            .create_option(|option| {
+                option
+                    .name("campaign")
+                    .description("The offer to claim, leave empty to see what you qualify for")
+                    .kind(CommandOptionType::String)
+            })
             .dm_permission(false)
     }
 }
-
-async fn respond(
-    command: &mut ApplicationCommandInteraction,
-    ctx: &Context,
-    reply: &str,
-) -> Result<(), CommandRuntimeError> {
-    command
-        .create_interaction_response(&ctx.http, |response| {
-            response
-                .kind(InteractionResponseType::ChannelMessageWithSource)
-                .interaction_response_data(|message| message.ephemeral(true).content(reply))
-        })
-        .await
-        .into_report()
-        .attach_printable("Failed to send interaction response")
-        .change_context(CommandRuntimeError)?;
-    Ok(())
-}
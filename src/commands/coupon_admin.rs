@@ -0,0 +1,505 @@
+use super::{ArgumentError, CommandOptions, CommandResponse, CommandRuntimeError};
+use crate::http::{CouponBuilder, CouponBuilderError, GMSCouponObject};
+use async_trait::async_trait;
+use chrono::{DateTime, Days, Utc};
+use error_stack::{Report, Result, ResultExt};
+use serenity::{
+    builder::{CreateApplicationCommand, CreateApplicationCommandOption, CreateEmbed},
+    client::Context,
+    model::{
+        application::{
+            command::CommandOptionType,
+            interaction::application_command::ApplicationCommandInteraction,
+        },
+        permissions::Permissions,
+    },
+};
+
+/// Most coupons shown by the list subcommand.
+const LIST_LIMIT: usize = 20;
+
+fn invalid(name: &str, reason: impl Into<String>) -> Report<CommandRuntimeError> {
+    Report::new(ArgumentError::Invalid {
+        name: name.to_string(),
+        reason: reason.into(),
+    })
+    .change_context(CommandRuntimeError)
+}
+
+/// Reports coupon validation failures against the option the staff member set.
+fn invalid_coupon(report: Report<CouponBuilderError>) -> Report<CommandRuntimeError> {
+    let error = report.current_context();
+    let name = match error.field {
+        "expires_at" => "days",
+        field => field,
+    };
+    let reason = error.reason;
+    report
+        .change_context(ArgumentError::Invalid {
+            name: name.to_string(),
+            reason: reason.to_string(),
+        })
+        .change_context(CommandRuntimeError)
+}
+
+fn parse_expiry(coupon: &GMSCouponObject) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(&coupon.expires_at)
+        .ok()
+        .map(Into::into)
+}
+
+fn timestamp(date: &str) -> String {
+    match DateTime::parse_from_rfc3339(date) {
+        Ok(date) => format!("<t:{}:f> (<t:{}:R>)", date.timestamp(), date.timestamp()),
+        Err(_) => date.to_string(),
+    }
+}
+
+fn summarise(coupon: &GMSCouponObject) -> String {
+    format!(
+        "`{}` {}% off, {} use(s), expires {} (ID `{}`)",
+        coupon.code,
+        coupon.percent,
+        coupon.max_uses,
+        timestamp(&coupon.expires_at),
+        coupon.id
+    )
+}
+
+fn describe(coupon: &GMSCouponObject) -> CreateEmbed {
+    let mut embed = CreateEmbed::default();
+    embed
+        .title(format!("Coupon `{}`", coupon.code))
+        .field("ID", format!("`{}`", coupon.id), false)
+        .field("Discount", format!("{}%", coupon.percent), true)
+        .field("Max Uses", coupon.max_uses, true)
+        .field(
+            "Bound User",
+            match &coupon.bound_user {
+                Some(id) => format!("https://www.gmodstore.com/users/{}", id),
+                None => "Anyone".to_string(),
+            },
+            false,
+        )
+        .field("Expires", timestamp(&coupon.expires_at), false)
+        .field("Created", timestamp(&coupon.created_at), false)
+        .colour(serenity::utils::Colour::from(0xBF8AE0));
+    embed
+}
+
+/// The GmodStore ID of the catalog product named by the `product` option.
+fn product_id<'a>(
+    handler: &'a crate::Handler,
+    options: &CommandOptions,
+) -> Result<(&'a crate::config::ProductConfig, &'a str), CommandRuntimeError> {
+    let key = options
+        .string("product")
+        .change_context(CommandRuntimeError)?;
+
+    let product = handler
+        .config
+        .products
+        .iter()
+        .find(|product| product.key.eq_ignore_ascii_case(key))
+        .ok_or_else(|| {
+            let known: Vec<_> = handler
+                .config
+                .products
+                .iter()
+                .filter(|product| product.gmodstore_id.is_some())
+                .map(|product| format!("`{}`", product.key))
+                .collect();
+            invalid("product", format!("it must be one of {}", known.join(", ")))
+        })?;
+
+    match &product.gmodstore_id {
+        Some(id) => Ok((product, id)),
+        None => Err(invalid(
+            "product",
+            format!("{} is not sold on GmodStore", product.name),
+        )),
+    }
+}
+
+/// The GmodStore account linked to the Discord user in the `user` option.
+async fn bound_user(
+    handler: &crate::Handler,
+    options: &CommandOptions<'_>,
+) -> Result<Option<String>, CommandRuntimeError> {
+    let Some(user) = options
+        .optional_user("user")
+        .change_context(CommandRuntimeError)?
+    else {
+        return Ok(None);
+    };
+
+    let linked = handler
+        .http
+        .link_client
+        .get_user_by_discord(user.id.0)
+        .await
+        .change_context(CommandRuntimeError)?;
+
+    match linked.and_then(|linked| linked.gmod_store_id) {
+        Some(id) => Ok(Some(id)),
+        None => Err(invalid(
+            "user",
+            format!("{} has not linked a GmodStore account", user.tag()),
+        )),
+    }
+}
+
+/// The `coupon` option, checked before it becomes part of a GmodStore API path.
+fn coupon_id<'a>(options: &CommandOptions<'a>) -> Result<&'a str, CommandRuntimeError> {
+    let coupon_id = options
+        .string("coupon")
+        .change_context(CommandRuntimeError)?;
+
+    match crate::http::is_gmodstore_id(coupon_id) {
+        true => Ok(coupon_id),
+        false => Err(invalid("coupon", "it is not a coupon ID")),
+    }
+}
+
+fn small_integer(
+    options: &CommandOptions,
+    name: &str,
+    default: Option<i64>,
+) -> Result<u8, CommandRuntimeError> {
+    let value = match default {
+        Some(default) => options.optional_integer(name).map(|v| v.unwrap_or(default)),
+        None => options.integer(name),
+    }
+    .change_context(CommandRuntimeError)?;
+
+    u8::try_from(value).map_err(|_| invalid(name, "it is out of range"))
+}
+
+async fn list(
+    handler: &crate::Handler,
+    options: CommandOptions<'_>,
+) -> Result<CommandResponse, CommandRuntimeError> {
+    let (product, product_id) = product_id(handler, &options)?;
+    let bound_user = bound_user(handler, &options).await?;
+    let active_only = options
+        .optional_boolean("active")
+        .change_context(CommandRuntimeError)?
+        .unwrap_or(true);
+
+    let mut coupons = handler
+        .http
+        .gmod_store_client
+        .get_product_coupons(product_id, bound_user.as_deref())
+        .await
+        .change_context(CommandRuntimeError)?;
+
+    if active_only {
+        let now = Utc::now();
+        coupons.retain(|coupon| parse_expiry(coupon).is_some_and(|expiry| expiry > now));
+    }
+
+    if coupons.is_empty() {
+        return Ok(format!("No coupons found for {}.", product.label()).into());
+    }
+
+    let lines: Vec<_> = coupons.iter().take(LIST_LIMIT).map(summarise).collect();
+
+    let mut embed = CreateEmbed::default();
+    embed
+        .title(format!("{} Coupons", product.label()))
+        .description(lines.join("\n"))
+        .footer(|footer| {
+            footer.text(format!(
+                "Showing {} of {} coupons",
+                lines.len(),
+                coupons.len()
+            ))
+        })
+        .colour(serenity::utils::Colour::from(0xBF8AE0));
+
+    Ok(embed.into())
+}
+
+async fn inspect(
+    handler: &crate::Handler,
+    options: CommandOptions<'_>,
+) -> Result<CommandResponse, CommandRuntimeError> {
+    let (_, product_id) = product_id(handler, &options)?;
+    let coupon_id = coupon_id(&options)?;
+
+    let coupon = handler
+        .http
+        .gmod_store_client
+        .get_coupon(product_id, coupon_id)
+        .await
+        .change_context(CommandRuntimeError)?;
+
+    Ok(match coupon {
+        Some(coupon) => describe(&coupon).into(),
+        None => format!("No coupon with ID `{}` exists.", coupon_id).into(),
+    })
+}
+
+async fn revoke(
+    handler: &crate::Handler,
+    options: CommandOptions<'_>,
+) -> Result<CommandResponse, CommandRuntimeError> {
+    let (_, product_id) = product_id(handler, &options)?;
+    let coupon_id = coupon_id(&options)?;
+
+    let client = &handler.http.gmod_store_client;
+    let Some(coupon) = client
+        .get_coupon(product_id, coupon_id)
+        .await
+        .change_context(CommandRuntimeError)?
+    else {
+        return Ok(format!("No coupon with ID `{}` exists.", coupon_id).into());
+    };
+
+    client
+        .delete_coupon(product_id, coupon_id)
+        .await
+        .change_context(CommandRuntimeError)?;
+
+    Ok(format!("Revoked coupon `{}`.", coupon.code).into())
+}
+
+async fn extend(
+    handler: &crate::Handler,
+    options: CommandOptions<'_>,
+) -> Result<CommandResponse, CommandRuntimeError> {
+    let (_, product_id) = product_id(handler, &options)?;
+    let coupon_id = coupon_id(&options)?;
+    let days = options
+        .integer("days")
+        .change_context(CommandRuntimeError)?;
+    let days = u64::try_from(days).map_err(|_| invalid("days", "it must be positive"))?;
+
+    let client = &handler.http.gmod_store_client;
+    let Some(coupon) = client
+        .get_coupon(product_id, coupon_id)
+        .await
+        .change_context(CommandRuntimeError)?
+    else {
+        return Ok(format!("No coupon with ID `{}` exists.", coupon_id).into());
+    };
+
+    // Expired coupons are extended from now rather than from their old expiry.
+    let from = parse_expiry(&coupon)
+        .filter(|expiry| *expiry > Utc::now())
+        .unwrap_or_else(Utc::now);
+    let expiry = from
+        .checked_add_days(Days::new(days))
+        .ok_or_else(|| invalid("days", "it is too far in the future"))?;
+
+    let builder = CouponBuilder::from_coupon(&coupon)
+        .expires_at(expiry)
+        .map_err(invalid_coupon)?;
+
+    let coupon = client
+        .update_coupon(product_id, coupon_id, builder)
+        .await
+        .change_context(CommandRuntimeError)?;
+
+    Ok(describe(&coupon).into())
+}
+
+async fn create(
+    handler: &crate::Handler,
+    options: CommandOptions<'_>,
+) -> Result<CommandResponse, CommandRuntimeError> {
+    let (_, product_id) = product_id(handler, &options)?;
+    let percent = small_integer(&options, "percent", None)?;
+    let max_uses = small_integer(&options, "max_uses", Some(1))?;
+    let days = options
+        .optional_integer("days")
+        .change_context(CommandRuntimeError)?
+        .unwrap_or(7);
+    let days = u64::try_from(days).map_err(|_| invalid("days", "it must be positive"))?;
+    let code = options
+        .optional_string("code")
+        .change_context(CommandRuntimeError)?
+        .map(str::to_string)
+        .unwrap_or_else(cuid2::cuid);
+    let bound_user = bound_user(handler, &options).await?;
+
+    let expiry = Utc::now()
+        .checked_add_days(Days::new(days))
+        .ok_or_else(|| invalid("days", "it is too far in the future"))?;
+
+    let builder = CouponBuilder::new(code, percent, max_uses, bound_user)
+        .and_then(|builder| builder.expires_at(expiry))
+        .map_err(invalid_coupon)?;
+
+    let coupon = handler
+        .http
+        .gmod_store_client
+        .create_coupon(product_id, builder)
+        .await
+        .change_context(CommandRuntimeError)?;
+
+    // Staff made coupons don't belong to a campaign.
+    crate::metrics::metrics()
+        .coupons_issued
+        .with_label_values(&["manual"])
+        .inc();
+
+    Ok(describe(&coupon).into())
+}
+
+fn product_option(
+    option: &mut CreateApplicationCommandOption,
+) -> &mut CreateApplicationCommandOption {
+    option
+        .name("product")
+        .description("Product key from the catalog, e.g. LSAC")
+        .kind(CommandOptionType::String)
+        .required(true)
+}
+
+fn coupon_option(
+    option: &mut CreateApplicationCommandOption,
+) -> &mut CreateApplicationCommandOption {
+    option
+        .name("coupon")
+        .description("Coupon ID, as shown by list")
+        .kind(CommandOptionType::String)
+        .required(true)
+}
+
+pub struct CouponAdminCommand;
+
+#[async_trait]
+impl super::Command for CouponAdminCommand {
+    async fn execute(
+        handler: &crate::Handler,
+        command: &ApplicationCommandInteraction,
+        _ctx: Context,
+    ) -> Result<CommandResponse, CommandRuntimeError> {
+        let Some((subcommand, options)) = CommandOptions::new(command).subcommand() else {
+            return Err(Report::new(CommandRuntimeError)
+                .attach_printable("coupon-admin was invoked without a subcommand"));
+        };
+
+        match subcommand {
+            "list" => list(handler, options).await,
+            "inspect" => inspect(handler, options).await,
+            "revoke" => revoke(handler, options).await,
+            "extend" => extend(handler, options).await,
+            "create" => create(handler, options).await,
+            other => Err(Report::new(CommandRuntimeError)
+                .attach_printable(format!("Unknown coupon-admin subcommand: {}", other))),
+        }
+    }
+
+    const DEFER: bool = true;
+
+    const AUDITED: bool = true;
+
+    fn name() -> &'static str {
+        "coupon-admin"
+    }
+
+    fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+        command
+            .description("Manage GmodStore coupons.")
+            .create_option(|subcommand| {
+                subcommand
+                    .name("list")
+                    .description("List the coupons of a product.")
+                    .kind(CommandOptionType::SubCommand)
+                    .create_sub_option(product_option)
+                    .create_sub_option(|option| {
+                        option
+                            .name("user")
+                            .description("Only coupons bound to this user")
+                            .kind(CommandOptionType::User)
+                    })
+                    .create_sub_option(|option| {
+                        option
+                            .name("active")
+                            .description("Only unexpired coupons, defaults to true")
+                            .kind(CommandOptionType::Boolean)
+                    })
+            })
+            .create_option(|subcommand| {
+                subcommand
+                    .name("inspect")
+                    .description("Show the details of a coupon.")
+                    .kind(CommandOptionType::SubCommand)
+                    .create_sub_option(product_option)
+                    .create_sub_option(coupon_option)
+            })
+            .create_option(|subcommand| {
+                subcommand
+                    .name("revoke")
+                    .description("Delete a coupon.")
+                    .kind(CommandOptionType::SubCommand)
+                    .create_sub_option(product_option)
+                    .create_sub_option(coupon_option)
+            })
+            .create_option(|subcommand| {
+                subcommand
+                    .name("extend")
+                    .description("Push back the expiry of a coupon.")
+                    .kind(CommandOptionType::SubCommand)
+                    .create_sub_option(product_option)
+                    .create_sub_option(coupon_option)
+                    .create_sub_option(|option| {
+                        option
+                            .name("days")
+                            .description("Days to add")
+                            .kind(CommandOptionType::Integer)
+                            .min_int_value(1)
+                            .max_int_value(365)
+                            .required(true)
+                    })
+            })
+            .create_option(|subcommand| {
+                subcommand
+                    .name("create")
+                    .description("Create a custom coupon.")
+                    .kind(CommandOptionType::SubCommand)
+                    .create_sub_option(product_option)
+                    .create_sub_option(|option| {
+                        option
+                            .name("percent")
+                            .description("Discount percentage")
+                            .kind(CommandOptionType::Integer)
+                            .min_int_value(1)
+                            .max_int_value(90)
+                            .required(true)
+                    })
+                    .create_sub_option(|option| {
+                        option
+                            .name("max_uses")
+                            .description("Times the coupon can be used, defaults to 1")
+                            .kind(CommandOptionType::Integer)
+                            .min_int_value(1)
+                            .max_int_value(100)
+                    })
+                    .create_sub_option(|option| {
+                        option
+                            .name("days")
+                            .description("Days until it expires, defaults to 7")
+                            .kind(CommandOptionType::Integer)
+                            .min_int_value(1)
+                            .max_int_value(365)
+                    })
+                    .create_sub_option(|option| {
+                        option
+                            .name("user")
+                            .description("Only this user may use the coupon")
+                            .kind(CommandOptionType::User)
+                    })
+                    .create_sub_option(|option| {
+                        option
+                            .name("code")
+                            .description("Coupon code, random if not set")
+                            .kind(CommandOptionType::String)
+                    })
+            })
+            .dm_permission(false)
+            .default_member_permissions(Permissions::MANAGE_GUILD)
+    }
+}
@@ -0,0 +1,147 @@
+use error_stack::Report;
+use serenity::{
+    http::HttpError,
+    model::{
+        error::Error as ModelError,
+        prelude::interaction::application_command::ApplicationCommandInteraction,
+    },
+    prelude::Context,
+    Error as SerenityError,
+};
+
+use super::{ArgumentError, CommandRuntimeError};
+use crate::http::{ApiError, GMSClientHTTPError, LinkClientHTTPError};
+
+/// An upstream API a command depends on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Upstream {
+    LinkApi,
+    GmodStore,
+}
+
+impl Upstream {
+    fn name(self) -> &'static str {
+        match self {
+            Self::LinkApi => "The linking site",
+            Self::GmodStore => "GmodStore",
+        }
+    }
+}
+
+/// What went wrong, as far as the user needs to know.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FailureKind {
+    Unavailable(Upstream),
+    Unauthorized(Upstream),
+    RateLimited(Upstream),
+    NotFound(Upstream),
+    Rejected(Upstream, String),
+    Permissions,
+    Internal,
+}
+
+impl FailureKind {
+    pub fn classify(report: &Report<CommandRuntimeError>) -> Self {
+        let missing_permissions = report
+            .frames()
+            .filter_map(|frame| frame.downcast_ref::<SerenityError>())
+            .any(|error| match error {
+                SerenityError::Model(ModelError::InvalidPermissions(_)) => true,
+                SerenityError::Http(error) => matches!(
+                    error.as_ref(),
+                    HttpError::UnsuccessfulRequest(response) if response.status_code.as_u16() == 403
+                ),
+                _ => false,
+            });
+
+        let upstream = if report.contains::<LinkClientHTTPError>() {
+            Upstream::LinkApi
+        } else if report.contains::<GMSClientHTTPError>() {
+            Upstream::GmodStore
+        } else if missing_permissions {
+            return Self::Permissions;
+        } else {
+            return Self::Internal;
+        };
+
+        match report.downcast_ref::<ApiError>() {
+            Some(ApiError::Unauthorized) => Self::Unauthorized(upstream),
+            Some(ApiError::RateLimited { .. }) => Self::RateLimited(upstream),
+            Some(ApiError::NotFound) => Self::NotFound(upstream),
+            Some(ApiError::Validation(message)) => Self::Rejected(upstream, message.clone()),
+            _ if missing_permissions => Self::Permissions,
+            _ => Self::Unavailable(upstream),
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            Self::Unavailable(upstream) => format!(
+                "{} is not responding right now, please try again in a few minutes.",
+                upstream.name()
+            ),
+            Self::Unauthorized(upstream) => format!(
+                "{} rejected my credentials, please let a staff member know.",
+                upstream.name()
+            ),
+            Self::RateLimited(upstream) => format!(
+                "{} is receiving too many requests, please try again in a minute.",
+                upstream.name()
+            ),
+            Self::NotFound(upstream) => format!(
+                "{} couldn't find what was asked for, it may have just been removed.",
+                upstream.name()
+            ),
+            Self::Rejected(upstream, message) => {
+                format!("{} rejected the request: {}", upstream.name(), message)
+            }
+            Self::Permissions => {
+                "I don't have the permissions needed to do that, please let a staff member know."
+                    .to_string()
+            }
+            Self::Internal => "Something went wrong whilst running this command.".to_string(),
+        }
+    }
+}
+
+/// Tells the user a command failed, reporting the error unless it was bad input.
+///
+/// Every reported failure gets a short reference, shown to the user and attached
+/// to both the log entry and the Sentry event so they can be matched up. That
+/// reference is returned, or `None` when the user just gave bad input.
+pub async fn report_failure(
+    command: &ApplicationCommandInteraction,
+    ctx: &Context,
+    deferred: bool,
+    report: Report<CommandRuntimeError>,
+) -> Option<String> {
+    let (content, reference) = if let Some(argument_error) = report.downcast_ref::<ArgumentError>()
+    {
+        debug!("Invalid command options: {}", argument_error);
+        (argument_error.to_string(), None)
+    } else {
+        let reference = cuid2::slug();
+        let kind = FailureKind::classify(&report);
+
+        sentry::with_scope(
+            |scope| {
+                scope.set_tag("error_ref", &reference);
+                scope.set_tag("command", &command.data.name);
+            },
+            || sentry::capture_error(&report.as_error()),
+        );
+        error!(
+            "[{}] Command {} failed ({:?}): {:#?}",
+            reference, command.data.name, kind, report
+        );
+
+        let content = format!("{}\nError reference: `{}`", kind.message(), reference);
+        (content, Some(reference))
+    };
+
+    if let Err(e) = super::response::send(command, ctx, deferred, content.into()).await {
+        error!("Failed to tell user about failed command: {:#?}", e);
+    }
+
+    reference
+}
@@ -1,15 +1,11 @@
-use super::CommandRuntimeError;
+use super::{CommandOptions, CommandResponse, CommandRuntimeError};
 use async_trait::async_trait;
-use error_stack::{IntoReport, Report, Result, ResultExt};
+use error_stack::{Result, ResultExt};
 use serenity::{
     builder::CreateApplicationCommand,
     client::Context,
     model::application::{
-        command::CommandOptionType,
-        interaction::{
-            application_command::{ApplicationCommandInteraction, CommandDataOptionValue},
-            InteractionResponseType,
-        },
+        command::CommandOptionType, interaction::application_command::ApplicationCommandInteraction,
     },
 };
 
@@ -19,27 +15,12 @@ pub struct GmodStoreCommand;
 impl super::Command for GmodStoreCommand {
     async fn execute(
         handler: &crate::Handler,
-        command: &mut ApplicationCommandInteraction,
-        ctx: Context,
-    ) -> Result<(), CommandRuntimeError> {
-        let target = match command.data.options.get(0) {
-            Some(target) => match target.resolved.as_ref() {
-                Some(target) => target,
-                None => {
-                    return Err(Report::new(CommandRuntimeError)
-                        .attach_printable("Failed to parse command target as a user"));
-                }
-            },
-            None => {
-                return Err(Report::new(CommandRuntimeError)
-                    .attach_printable("Failed to get command target"));
-            }
-        };
-
-        let CommandDataOptionValue::User(user, _member) = target else {
-            return Err(Report::new(CommandRuntimeError)
-                .attach_printable("Failed to fetch and validate user from command target"));
-        };
+        command: &ApplicationCommandInteraction,
+        _ctx: Context,
+    ) -> Result<CommandResponse, CommandRuntimeError> {
+        let user = CommandOptions::new(command)
+            .user("user")
+            .change_context(CommandRuntimeError)?;
 
         let api_response = handler
             .http
@@ -56,25 +37,15 @@ impl super::Command for GmodStoreCommand {
             None => "User is not linked.".to_string(),
         };
 
-        command
-            .create_interaction_response(&ctx.http, |response| {
-                response
-                    .kind(InteractionResponseType::ChannelMessageWithSource)
-                    .interaction_response_data(|message| {
-                        message.ephemeral(true).content(interaction_reply)
-                    })
-            })
-            .await
-            .into_report()
-            .attach_printable("Failed to send interaction response")
-            .change_context(CommandRuntimeError)?;
+        Ok(interaction_reply.into())
+    }
 
-        Ok(())
+    fn name() -> &'static str {
+        "gmodstore"
     }
 
     fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
         command
-            .name("gmodstore")
             .description("Retrieve user GmodStore account page.")
             .create_option(|option| {
                 option
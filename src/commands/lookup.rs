@@ -0,0 +1,146 @@
+use super::{ArgumentError, CommandOptions, CommandResponse, CommandRuntimeError};
+use crate::steamid::SteamId;
+use async_trait::async_trait;
+use error_stack::{Report, Result, ResultExt};
+use serenity::{
+    builder::{CreateApplicationCommand, CreateEmbed},
+    client::Context,
+    model::{
+        application::{
+            command::CommandOptionType,
+            interaction::application_command::ApplicationCommandInteraction,
+        },
+        id::UserId,
+        mention::Mention,
+        permissions::Permissions,
+    },
+};
+
+/// An account identifier a staff member can look a user up by.
+enum LookupQuery {
+    Steam(SteamId),
+    GmodStore(String),
+}
+
+impl LookupQuery {
+    fn parse(input: &str) -> std::result::Result<Self, String> {
+        let input = input.trim();
+
+        let gmodstore_path = input
+            .trim_start_matches("https://")
+            .trim_start_matches("http://")
+            .trim_start_matches("www.")
+            .strip_prefix("gmodstore.com/users/");
+
+        if let Some(path) = gmodstore_path {
+            let id = path.split(['/', '?', '#']).next().unwrap_or_default();
+            if id.is_empty() {
+                return Err("the GmodStore profile URL has no user in it".to_string());
+            }
+            if !crate::http::is_gmodstore_id(id) {
+                return Err("the GmodStore profile URL has an invalid user in it".to_string());
+            }
+
+            // GmodStore profiles can also be addressed by SteamID64.
+            return Ok(match id.parse::<SteamId>() {
+                Ok(steam_id) => Self::Steam(steam_id),
+                Err(_) => Self::GmodStore(id.to_string()),
+            });
+        }
+
+        input
+            .parse::<SteamId>()
+            .map(Self::Steam)
+            .map_err(|e| e.to_string())
+    }
+}
+
+pub struct LookupCommand;
+
+#[async_trait]
+impl super::Command for LookupCommand {
+    async fn execute(
+        handler: &crate::Handler,
+        command: &ApplicationCommandInteraction,
+        _ctx: Context,
+    ) -> Result<CommandResponse, CommandRuntimeError> {
+        let account = CommandOptions::new(command)
+            .string("account")
+            .change_context(CommandRuntimeError)?;
+
+        let query = LookupQuery::parse(account).map_err(|reason| {
+            Report::new(ArgumentError::Invalid {
+                name: "account".to_string(),
+                reason,
+            })
+            .change_context(CommandRuntimeError)
+        })?;
+
+        let link_client = &handler.http.link_client;
+        let user = match &query {
+            LookupQuery::Steam(steam_id) => link_client.get_user_by_steam(steam_id.as_u64()).await,
+            LookupQuery::GmodStore(id) => link_client.get_user_by_gmodstore(id).await,
+        }
+        .change_context(CommandRuntimeError)?;
+
+        let Some(user) = user else {
+            return Ok(format!("No linked account was found for `{}`.", account).into());
+        };
+
+        let mut embed = CreateEmbed::default();
+        embed
+            .title(user.name.as_deref().unwrap_or("Linked Account"))
+            .field(
+                "Discord",
+                match user.discord_id {
+                    Some(id) => format!("{} (`{}`)", Mention::User(UserId(id)), id),
+                    None => "Not linked".to_string(),
+                },
+                false,
+            )
+            .field(
+                "Steam",
+                match SteamId::from_u64(user.steam_id) {
+                    Ok(steam_id) => format!("{}\n`{}`", steam_id.profile_url(), steam_id.steam2()),
+                    Err(_) => format!("`{}`", user.steam_id),
+                },
+                false,
+            )
+            .field(
+                "GmodStore",
+                match &user.gmod_store_id {
+                    Some(id) => format!("https://www.gmodstore.com/users/{}", id),
+                    None => "Not linked".to_string(),
+                },
+                false,
+            )
+            .field("Linked Since", &user.created_at, false)
+            .colour(serenity::utils::Colour::from(0xBF8AE0));
+
+        if let Some(avatar) = &user.avatar {
+            embed.thumbnail(avatar);
+        }
+
+        Ok(embed.into())
+    }
+
+    const AUDITED: bool = true;
+
+    fn name() -> &'static str {
+        "lookup"
+    }
+
+    fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
+        command
+            .description("Find the Discord account linked to a Steam or GmodStore account.")
+            .create_option(|option| {
+                option
+                    .name("account")
+                    .description("SteamID64, SteamID2/3, or a Steam or GmodStore profile URL")
+                    .kind(CommandOptionType::String)
+                    .required(true)
+            })
+            .dm_permission(false)
+            .default_member_permissions(Permissions::MODERATE_MEMBERS)
+    }
+}
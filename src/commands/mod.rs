@@ -6,30 +6,58 @@ use serenity::{
     prelude::Context,
 };
 
+mod audit;
 mod coupon;
+mod coupon_admin;
+mod failure;
 mod forceroles;
 mod gmodstore;
+mod lookup;
+mod options;
 mod purchases;
+mod registration;
+mod registry;
+mod response;
 mod roles;
 mod steam;
 mod unlink;
 
-pub use coupon::CouponCommand;
+pub use audit::AuditCommand;
+pub use coupon::{ClaimLocks, CouponCommand};
+pub use coupon_admin::CouponAdminCommand;
+pub use failure::report_failure;
 pub use forceroles::ForceRolesCommand;
 pub use gmodstore::GmodStoreCommand;
+pub use lookup::LookupCommand;
+pub use options::{ArgumentError, CommandOptions};
 pub use purchases::PurchasesCommand;
+pub use registration::{register_commands, RegistrationMode};
+pub use registry::CommandRegistry;
+pub use response::{send as send_response, CommandResponse};
 pub use roles::RolesCommand;
 pub use steam::SteamCommand;
 pub use unlink::UnlinkCommand;
 
 #[async_trait]
 pub trait Command {
+    /// Name the command is registered and dispatched under.
+    fn name() -> &'static str;
+
+    /// Defer straight away instead of waiting to see if the command is slow.
+    ///
+    /// Set this for commands that always chain several upstream calls.
+    const DEFER: bool = false;
+
+    /// Record every run in the audit log, for commands that act on other members.
+    const AUDITED: bool = false;
+
     async fn execute(
         handler: &crate::Handler,
-        ctx: &mut ApplicationCommandInteraction,
+        ctx: &ApplicationCommandInteraction,
         interaction: Context,
-    ) -> Result<(), CommandRuntimeError>;
+    ) -> Result<CommandResponse, CommandRuntimeError>;
 
+    /// Describes the command, the name is already set by the registry.
     fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand;
 }
 
@@ -0,0 +1,170 @@
+use error_stack::{Context as ErrorContext, Report, Result};
+use serenity::model::{
+    application::{
+        command::CommandOptionType,
+        interaction::application_command::{
+            ApplicationCommandInteraction, CommandDataOption, CommandDataOptionValue,
+        },
+    },
+    guild::PartialMember,
+    mention::Mention,
+    user::User,
+};
+
+/// A problem with the options a user supplied.
+///
+/// The `Display` output is shown to the user as-is, so keep it friendly.
+#[derive(Debug)]
+pub enum ArgumentError {
+    Missing(String),
+    WrongType {
+        name: String,
+        expected: &'static str,
+    },
+    NotAMember(User),
+    /// The value has the right type but can't be used, `reason` is shown to the user.
+    Invalid {
+        name: String,
+        reason: String,
+    },
+}
+
+impl std::fmt::Display for ArgumentError {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Missing(name) => write!(fmt, "The `{}` option is required.", name),
+            Self::WrongType { name, expected } => {
+                write!(fmt, "The `{}` option must be {}.", name, expected)
+            }
+            Self::NotAMember(user) => {
+                write!(
+                    fmt,
+                    "{} is not a member of this server.",
+                    Mention::User(user.id)
+                )
+            }
+            Self::Invalid { name, reason } => {
+                write!(fmt, "The `{}` option is invalid: {}.", name, reason)
+            }
+        }
+    }
+}
+
+impl ErrorContext for ArgumentError {}
+
+/// Named, typed access to the options of a slash command.
+pub struct CommandOptions<'a> {
+    options: &'a [CommandDataOption],
+}
+
+impl<'a> CommandOptions<'a> {
+    pub fn new(command: &'a ApplicationCommandInteraction) -> Self {
+        Self {
+            options: &command.data.options,
+        }
+    }
+
+    fn resolved(&self, name: &str) -> Option<&'a CommandDataOptionValue> {
+        self.options
+            .iter()
+            .find(|option| option.name == name)
+            .and_then(|option| option.resolved.as_ref())
+    }
+
+    fn required<T>(
+        &self,
+        name: &str,
+        value: Result<Option<T>, ArgumentError>,
+    ) -> Result<T, ArgumentError> {
+        value?.ok_or_else(|| Report::new(ArgumentError::Missing(name.to_string())))
+    }
+
+    fn wrong_type(name: &str, expected: &'static str) -> Report<ArgumentError> {
+        Report::new(ArgumentError::WrongType {
+            name: name.to_string(),
+            expected,
+        })
+    }
+
+    /// The subcommand that was invoked, with its own options.
+    pub fn subcommand(&self) -> Option<(&'a str, CommandOptions<'a>)> {
+        self.options
+            .iter()
+            .find(|option| option.kind == CommandOptionType::SubCommand)
+            .map(|option| {
+                (
+                    option.name.as_str(),
+                    CommandOptions {
+                        options: &option.options,
+                    },
+                )
+            })
+    }
+
+    pub fn optional_string(&self, name: &str) -> Result<Option<&'a str>, ArgumentError> {
+        match self.resolved(name) {
+            None => Ok(None),
+            Some(CommandDataOptionValue::String(value)) => Ok(Some(value.as_str())),
+            Some(_) => Err(Self::wrong_type(name, "text")),
+        }
+    }
+
+    pub fn string(&self, name: &str) -> Result<&'a str, ArgumentError> {
+        self.required(name, self.optional_string(name))
+    }
+
+    pub fn optional_integer(&self, name: &str) -> Result<Option<i64>, ArgumentError> {
+        match self.resolved(name) {
+            None => Ok(None),
+            Some(CommandDataOptionValue::Integer(value)) => Ok(Some(*value)),
+            Some(_) => Err(Self::wrong_type(name, "a whole number")),
+        }
+    }
+
+    pub fn integer(&self, name: &str) -> Result<i64, ArgumentError> {
+        self.required(name, self.optional_integer(name))
+    }
+
+    pub fn optional_boolean(&self, name: &str) -> Result<Option<bool>, ArgumentError> {
+        match self.resolved(name) {
+            None => Ok(None),
+            Some(CommandDataOptionValue::Boolean(value)) => Ok(Some(*value)),
+            Some(_) => Err(Self::wrong_type(name, "true or false")),
+        }
+    }
+
+    pub fn optional_user_with_member(
+        &self,
+        name: &str,
+    ) -> Result<Option<(&'a User, Option<&'a PartialMember>)>, ArgumentError> {
+        match self.resolved(name) {
+            None => Ok(None),
+            Some(CommandDataOptionValue::User(user, member)) => Ok(Some((user, member.as_ref()))),
+            Some(_) => Err(Self::wrong_type(name, "a user")),
+        }
+    }
+
+    /// A user option, along with their guild membership if they are in the guild.
+    pub fn user_with_member(
+        &self,
+        name: &str,
+    ) -> Result<(&'a User, Option<&'a PartialMember>), ArgumentError> {
+        self.required(name, self.optional_user_with_member(name))
+    }
+
+    pub fn optional_user(&self, name: &str) -> Result<Option<&'a User>, ArgumentError> {
+        Ok(self.optional_user_with_member(name)?.map(|(user, _)| user))
+    }
+
+    pub fn user(&self, name: &str) -> Result<&'a User, ArgumentError> {
+        self.required(name, self.optional_user(name))
+    }
+
+    /// A user option that must refer to a member of the guild.
+    pub fn member(&self, name: &str) -> Result<(&'a User, &'a PartialMember), ArgumentError> {
+        match self.user_with_member(name)? {
+            (user, Some(member)) => Ok((user, member)),
+            (user, None) => Err(Report::new(ArgumentError::NotAMember(user.clone()))),
+        }
+    }
+}
@@ -1,17 +1,14 @@
-use super::CommandRuntimeError;
+use super::{CommandOptions, CommandResponse, CommandRuntimeError};
 use crate::misc::bool_to_emoji as emoji_parse;
 use async_trait::async_trait;
-use error_stack::{IntoReport, Report, Result, ResultExt};
+use error_stack::{Result, ResultExt};
 use serenity::{
     builder::{CreateApplicationCommand, CreateEmbed, CreateEmbedAuthor},
     client::Context,
     model::{
         application::{
             command::CommandOptionType,
-            interaction::{
-                application_command::{ApplicationCommandInteraction, CommandDataOptionValue},
-                InteractionResponseType,
-            },
+            interaction::application_command::ApplicationCommandInteraction,
         },
         Permissions,
     },
@@ -23,27 +20,12 @@ pub struct PurchasesCommand;
 impl super::Command for PurchasesCommand {
     async fn execute(
         handler: &crate::Handler,
-        command: &mut ApplicationCommandInteraction,
-        ctx: Context,
-    ) -> Result<(), CommandRuntimeError> {
-        let target = match command.data.options.get(0) {
-            Some(target) => match target.resolved.as_ref() {
-                Some(target) => target,
-                None => {
-                    return Err(Report::new(CommandRuntimeError)
-                        .attach_printable("Failed to parse command target as a user"));
-                }
-            },
-            None => {
-                return Err(Report::new(CommandRuntimeError)
-                    .attach_printable("Failed to get command target"));
-            }
-        };
-
-        let CommandDataOptionValue::User(user, _member) = target else {
-            return Err(Report::new(CommandRuntimeError)
-                .attach_printable("Failed to fetch and validate user from command target"));
-        };
+        command: &ApplicationCommandInteraction,
+        _ctx: Context,
+    ) -> Result<CommandResponse, CommandRuntimeError> {
+        let user = CommandOptions::new(command)
+            .user("user")
+            .change_context(CommandRuntimeError)?;
 
         let api_response = handler
             .http
@@ -71,21 +53,30 @@ impl super::Command for PurchasesCommand {
                     .get_purchases()
                     .await
                     .change_context(CommandRuntimeError)?;
-                let message_contents = format!(
-                    r#"{} | Ley's Server-Side AntiCheat
-                {} | SwiftAC
-                {} | Ley's HitReg
-                {} | Ley's Screengrabs
-                {} | Ley WorkshopDL
-                {} | Ley Sexy Errors"#,
-                    emoji_parse(purchases.lsac),
-                    emoji_parse(purchases.swift_ac),
-                    emoji_parse(purchases.hit_reg),
-                    emoji_parse(purchases.screen_grabs),
-                    emoji_parse(purchases.workshop_dl),
-                    emoji_parse(purchases.sexy_errors)
+                let mut lines: Vec<String> = handler
+                    .config
+                    .products
+                    .iter()
+                    .map(|product| {
+                        format!(
+                            "{} | {}",
+                            emoji_parse(purchases.owns(&product.key)),
+                            product.label()
+                        )
+                    })
+                    .collect();
+
+                // Products the linking site knows about but the catalog doesn't yet.
+                lines.extend(
+                    purchases
+                        .0
+                        .iter()
+                        .filter(|(key, _)| handler.config.product(key).is_none())
+                        .map(|(key, owned)| format!("{} | {}", emoji_parse(*owned), key)),
                 );
 
+                let message_contents = lines.join("\n");
+
                 message_reply.description(message_contents);
             }
             None => {
@@ -95,25 +86,17 @@ impl super::Command for PurchasesCommand {
             }
         };
 
-        command
-            .create_interaction_response(&ctx.http, |response| {
-                response
-                    .kind(InteractionResponseType::ChannelMessageWithSource)
-                    .interaction_response_data(|message| {
-                        message.ephemeral(true).add_embed(message_reply)
-                    })
-            })
-            .await
-            .into_report()
-            .attach_printable("Failed to send interaction response")
-            .change_context(CommandRuntimeError)?;
+        Ok(message_reply.into())
+    }
+
+    const AUDITED: bool = true;
 
-        Ok(())
+    fn name() -> &'static str {
+        "purchases"
     }
 
     fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
         command
-            .name("purchases")
             .description("Retrieve user's GmodStore purchases.")
             .create_option(|option| {
                 option
@@ -0,0 +1,131 @@
+use super::CommandRegistry;
+use error_stack::{Context as ErrorContext, IntoReport, Report, Result, ResultExt};
+use serenity::{
+    http::Http,
+    model::{application::command::Command as InteractionCommand, id::GuildId},
+};
+
+#[derive(Debug)]
+pub struct CommandRegistrationError;
+
+impl std::fmt::Display for CommandRegistrationError {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt.write_str("Bot Error: An error occurred whilst registering slash commands")
+    }
+}
+
+impl ErrorContext for CommandRegistrationError {}
+
+/// Where slash commands are pushed to.
+///
+/// Guild commands update instantly, global commands can take up to an hour to
+/// propagate, so `guild` is the mode to use during development.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegistrationMode {
+    Global,
+    Guild,
+    Both,
+}
+
+impl RegistrationMode {
+    /// Reads `COMMAND_REGISTRATION`, defaulting to `global` when unset.
+    pub fn from_env() -> Result<Self, CommandRegistrationError> {
+        let mode = crate::misc::get_env("COMMAND_REGISTRATION").unwrap_or_default();
+
+        match mode.to_lowercase().as_str() {
+            "" | "global" => Ok(Self::Global),
+            "guild" => Ok(Self::Guild),
+            "both" => Ok(Self::Both),
+            _ => Err(
+                Report::new(CommandRegistrationError).attach_printable(format!(
+                    "Invalid COMMAND_REGISTRATION '{}', expected global, guild or both",
+                    mode
+                )),
+            ),
+        }
+    }
+
+    pub fn requires_guild(self) -> bool {
+        self != Self::Global
+    }
+}
+
+/// Pushes the command list to every scope the mode covers.
+///
+/// Scopes the mode doesn't cover are cleared, so switching modes never leaves
+/// duplicate or stale commands behind.
+pub async fn register_commands(
+    http: &Http,
+    registry: &CommandRegistry,
+    mode: RegistrationMode,
+    guild_id: Option<GuildId>,
+) -> Result<(), CommandRegistrationError> {
+    let global = mode != RegistrationMode::Guild;
+    let guild = mode != RegistrationMode::Global;
+
+    let existing = InteractionCommand::get_global_application_commands(http)
+        .await
+        .into_report()
+        .attach_printable("Failed to fetch global slash commands")
+        .change_context(CommandRegistrationError)?;
+
+    let pushed = InteractionCommand::set_global_application_commands(http, |commands| {
+        if global {
+            registry.create_commands(commands)
+        } else {
+            commands
+        }
+    })
+    .await
+    .into_report()
+    .attach_printable("Failed to push global slash commands")
+    .change_context(CommandRegistrationError)?;
+
+    log_changes("global", &existing, &pushed);
+
+    let Some(guild_id) = guild_id else {
+        if guild {
+            return Err(Report::new(CommandRegistrationError)
+                .attach_printable("DISCORD_GUILD must be set to register guild commands"));
+        }
+        return Ok(());
+    };
+
+    let existing = guild_id
+        .get_application_commands(http)
+        .await
+        .into_report()
+        .attach_printable("Failed to fetch guild slash commands")
+        .change_context(CommandRegistrationError)?;
+
+    let pushed = guild_id
+        .set_application_commands(http, |commands| {
+            if guild {
+                registry.create_commands(commands)
+            } else {
+                commands
+            }
+        })
+        .await
+        .into_report()
+        .attach_printable("Failed to push guild slash commands")
+        .change_context(CommandRegistrationError)?;
+
+    log_changes("guild", &existing, &pushed);
+
+    Ok(())
+}
+
+fn log_changes(scope: &str, existing: &[InteractionCommand], pushed: &[InteractionCommand]) {
+    debug!("Pushed {} {} slash commands!", pushed.len(), scope);
+
+    for command in pushed {
+        debug!("Registered {} command: {}", scope, command.name);
+    }
+
+    for command in existing {
+        if !pushed.iter().any(|c| c.name == command.name) {
+            info!("Removed stale {} command: {}", scope, command.name);
+        }
+    }
+}
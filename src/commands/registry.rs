@@ -0,0 +1,217 @@
+use error_stack::Result;
+use futures::future::BoxFuture;
+use serenity::{
+    builder::{CreateApplicationCommand, CreateApplicationCommands},
+    model::id::UserId,
+    model::prelude::interaction::application_command::ApplicationCommandInteraction,
+    prelude::Context,
+};
+use std::time::{Duration, Instant};
+
+use super::{response, Command, CommandResponse, CommandRuntimeError};
+use crate::audit::{self, LinkState, Outcome};
+
+/// Discord fails interactions that aren't acknowledged within 3 seconds.
+const AUTO_DEFER_AFTER: Duration = Duration::from_secs(2);
+
+type RegisterFn = fn(&mut CreateApplicationCommand) -> &mut CreateApplicationCommand;
+type ExecuteFn = for<'a> fn(
+    &'a crate::Handler,
+    &'a ApplicationCommandInteraction,
+    Context,
+) -> BoxFuture<'a, Result<CommandResponse, CommandRuntimeError>>;
+
+pub struct RegisteredCommand {
+    pub name: &'static str,
+    defer: bool,
+    audited: bool,
+    register: RegisterFn,
+    execute: ExecuteFn,
+}
+
+impl RegisteredCommand {
+    fn new<C: Command>() -> Self {
+        Self {
+            name: C::name(),
+            defer: C::DEFER,
+            audited: C::AUDITED,
+            register: C::register,
+            execute: |handler, command, ctx| C::execute(handler, command, ctx),
+        }
+    }
+
+    /// Runs the command and sends its response, or tells the user it failed.
+    ///
+    /// Commands that take longer than [`AUTO_DEFER_AFTER`] are deferred while
+    /// they keep running, so slow upstream calls never fail the interaction.
+    /// Audited commands are recorded with their target's link state either side.
+    pub async fn run(
+        &self,
+        handler: &crate::Handler,
+        command: &ApplicationCommandInteraction,
+        ctx: Context,
+    ) {
+        if !self.audited {
+            self.measure(handler, command, ctx, None).await;
+            return;
+        }
+
+        let target = audit::target(command);
+        let (outcome, before) = self.measure(handler, command, ctx.clone(), target).await;
+
+        let after = match target {
+            Some(target) => LinkState::fetch(handler, target).await,
+            None => None,
+        };
+
+        let entry = audit::entry(command, target, before, after, &outcome);
+        audit::record(&ctx.http, handler, entry).await;
+    }
+
+    async fn measure(
+        &self,
+        handler: &crate::Handler,
+        command: &ApplicationCommandInteraction,
+        ctx: Context,
+        target: Option<UserId>,
+    ) -> (Outcome, Option<LinkState>) {
+        let started = Instant::now();
+        let (outcome, before) = self.respond(handler, command, ctx, target).await;
+
+        let metrics = crate::metrics::metrics();
+        metrics
+            .commands
+            .with_label_values(&[self.name, outcome.label()])
+            .inc();
+        metrics
+            .command_duration
+            .with_label_values(&[self.name])
+            .observe(started.elapsed().as_secs_f64());
+
+        (outcome, before)
+    }
+
+    /// Also snapshots `target`'s link state just before executing, for the audit log.
+    async fn respond(
+        &self,
+        handler: &crate::Handler,
+        command: &ApplicationCommandInteraction,
+        ctx: Context,
+        target: Option<UserId>,
+    ) -> (Outcome, Option<LinkState>) {
+        let mut deferred = false;
+
+        if self.defer {
+            if let Err(e) = response::defer(command, &ctx).await {
+                return (Self::failed(command, &ctx, false, e).await, None);
+            }
+            deferred = true;
+        }
+
+        // The snapshot is part of the execution, so it's covered by the deferral too.
+        let mut execution = Box::pin(async {
+            let before = match target {
+                Some(target) => LinkState::fetch(handler, target).await,
+                None => None,
+            };
+            (before, (self.execute)(handler, command, ctx.clone()).await)
+        });
+
+        let (before, result) = if deferred {
+            execution.await
+        } else {
+            tokio::select! {
+                executed = &mut execution => executed,
+                _ = tokio::time::sleep(AUTO_DEFER_AFTER) => {
+                    debug!("Command {} is taking a while, deferring response", self.name);
+                    match response::defer(command, &ctx).await {
+                        Ok(()) => deferred = true,
+                        Err(e) => warn!("{:#?}", e),
+                    }
+                    execution.await
+                }
+            }
+        };
+
+        let result = match result {
+            Ok(reply) => response::send(command, &ctx, deferred, reply).await,
+            Err(e) => Err(e),
+        };
+
+        let outcome = match result {
+            Ok(()) => Outcome::Succeeded,
+            Err(e) => {
+                debug!("An error occurred whilst running previous command");
+                Self::failed(command, &ctx, deferred, e).await
+            }
+        };
+        (outcome, before)
+    }
+
+    async fn failed(
+        command: &ApplicationCommandInteraction,
+        ctx: &Context,
+        deferred: bool,
+        report: error_stack::Report<CommandRuntimeError>,
+    ) -> Outcome {
+        match super::report_failure(command, ctx, deferred, report).await {
+            Some(reference) => Outcome::Failed { reference },
+            None => Outcome::Rejected,
+        }
+    }
+}
+
+/// The single list of slash commands, used for both registration and dispatch.
+pub struct CommandRegistry {
+    commands: Vec<RegisteredCommand>,
+}
+
+impl CommandRegistry {
+    pub fn new() -> Self {
+        Self {
+            commands: Vec::new(),
+        }
+    }
+
+    pub fn with<C: Command>(mut self) -> Self {
+        assert!(
+            self.get(C::name()).is_none(),
+            "Command registered twice: {}",
+            C::name()
+        );
+        self.commands.push(RegisteredCommand::new::<C>());
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&RegisteredCommand> {
+        self.commands.iter().find(|command| command.name == name)
+    }
+
+    pub fn create_commands<'a>(
+        &self,
+        commands: &'a mut CreateApplicationCommands,
+    ) -> &'a mut CreateApplicationCommands {
+        for registered in &self.commands {
+            commands.create_application_command(|command| {
+                (registered.register)(command.name(registered.name))
+            });
+        }
+        commands
+    }
+}
+
+impl Default for CommandRegistry {
+    fn default() -> Self {
+        Self::new()
+            .with::<super::AuditCommand>()
+            .with::<super::CouponCommand>()
+            .with::<super::CouponAdminCommand>()
+            .with::<super::ForceRolesCommand>()
+            .with::<super::GmodStoreCommand>()
+            .with::<super::LookupCommand>()
+            .with::<super::PurchasesCommand>()
+            .with::<super::RolesCommand>()
+            .with::<super::SteamCommand>()
+            .with::<super::UnlinkCommand>()
+    }
+}
@@ -0,0 +1,87 @@
+use error_stack::{IntoReport, Result, ResultExt};
+use serenity::{
+    builder::CreateEmbed,
+    model::prelude::interaction::{
+        application_command::ApplicationCommandInteraction, InteractionResponseType,
+    },
+    prelude::Context,
+};
+
+use super::CommandRuntimeError;
+
+/// What a command replies with, always sent ephemerally.
+pub enum CommandResponse {
+    Content(String),
+    Embed(CreateEmbed),
+}
+
+impl From<String> for CommandResponse {
+    fn from(content: String) -> Self {
+        Self::Content(content)
+    }
+}
+
+impl From<&str> for CommandResponse {
+    fn from(content: &str) -> Self {
+        Self::Content(content.to_string())
+    }
+}
+
+impl From<CreateEmbed> for CommandResponse {
+    fn from(embed: CreateEmbed) -> Self {
+        Self::Embed(embed)
+    }
+}
+
+/// Acknowledges the interaction so Discord waits for the real response.
+pub async fn defer(
+    command: &ApplicationCommandInteraction,
+    ctx: &Context,
+) -> Result<(), CommandRuntimeError> {
+    command
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::DeferredChannelMessageWithSource)
+                .interaction_response_data(|message| message.ephemeral(true))
+        })
+        .await
+        .into_report()
+        .attach_printable("Failed to defer interaction response")
+        .change_context(CommandRuntimeError)?;
+    Ok(())
+}
+
+/// Sends the response, editing the deferred placeholder if there is one.
+pub async fn send(
+    command: &ApplicationCommandInteraction,
+    ctx: &Context,
+    deferred: bool,
+    response: CommandResponse,
+) -> Result<(), CommandRuntimeError> {
+    if deferred {
+        command
+            .edit_original_interaction_response(&ctx.http, |message| match response {
+                CommandResponse::Content(content) => message.content(content),
+                CommandResponse::Embed(embed) => message.add_embed(embed),
+            })
+            .await
+            .into_report()
+            .attach_printable("Failed to edit deferred interaction response")
+            .change_context(CommandRuntimeError)?;
+    } else {
+        command
+            .create_interaction_response(&ctx.http, |message| {
+                message
+                    .kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|data| match response {
+                        CommandResponse::Content(content) => data.ephemeral(true).content(content),
+                        CommandResponse::Embed(embed) => data.ephemeral(true).add_embed(embed),
+                    })
+            })
+            .await
+            .into_report()
+            .attach_printable("Failed to send interaction response")
+            .change_context(CommandRuntimeError)?;
+    }
+    Ok(())
+}
@@ -1,12 +1,9 @@
-use super::CommandRuntimeError;
+use super::{CommandResponse, CommandRuntimeError};
 use async_trait::async_trait;
-use error_stack::{IntoReport, Report, Result, ResultExt};
+use error_stack::{Report, Result, ResultExt};
 use serenity::{
-    builder::CreateApplicationCommand,
-    client::Context,
-    model::application::interaction::{
-        application_command::ApplicationCommandInteraction, InteractionResponseType,
-    },
+    builder::CreateApplicationCommand, client::Context,
+    model::application::interaction::application_command::ApplicationCommandInteraction,
 };
 
 pub struct RolesCommand;
@@ -15,10 +12,10 @@ pub struct RolesCommand;
 impl super::Command for RolesCommand {
     async fn execute(
         handler: &crate::Handler,
-        command: &mut ApplicationCommandInteraction,
+        command: &ApplicationCommandInteraction,
         ctx: Context,
-    ) -> Result<(), CommandRuntimeError> {
-        let member = match &mut command.member {
+    ) -> Result<CommandResponse, CommandRuntimeError> {
+        let mut member = match command.member.clone() {
             Some(member) => member,
             None => {
                 return Err(Report::new(CommandRuntimeError)
@@ -33,88 +30,40 @@ impl super::Command for RolesCommand {
             .await
             .change_context(CommandRuntimeError)?;
 
+        let purchases = match &api_response {
+            Some(user) => Some(
+                user.get_purchases()
+                    .await
+                    .change_context(CommandRuntimeError)?,
+            ),
+            None => None,
+        };
+
+        let diff =
+            crate::roles::reconcile(&ctx.http, &mut member, &handler.config, purchases.as_ref())
+                .await
+                .change_context(CommandRuntimeError)?;
+
         let interaction_response = match api_response {
-            Some(response) => {
-                let purchases = response.get_purchases().await.change_context(CommandRuntimeError)?;
-                if purchases.lsac {
-                    member
-                        .add_role(&ctx.http, 884061162482847765)
-                        .await
-                        .into_report()
-                        .attach_printable("Failed to add LSAC role")
-                        .change_context(CommandRuntimeError)?;
-                }
-    
-                if purchases.swift_ac {
-                    member
-                        .add_role(&ctx.http, 884060408946757663)
-                        .await
-                        .into_report()
-                        .attach_printable("Failed to add SwiftAC role")
-                        .change_context(CommandRuntimeError)?;
-                }
-    
-                if purchases.hit_reg {
-                    member
-                        .add_role(&ctx.http, 884060954294386698)
-                        .await
-                        .into_report()
-                        .attach_printable("Failed to add HitReg role")
-                        .change_context(CommandRuntimeError)?;
-                }
-    
-                if purchases.screen_grabs {
-                    member
-                        .add_role(&ctx.http, 889306784551026780)
-                        .await
-                        .into_report()
-                        .attach_printable("Failed to add ScreenGrabs role")
-                        .change_context(CommandRuntimeError)?;
-                }
-    
-                if purchases.workshop_dl {
-                    member
-                        .add_role(&ctx.http, 884060628128497716)
-                        .await
-                        .into_report()
-                        .attach_printable("Failed to add WorkshopDL role")
-                        .change_context(CommandRuntimeError)?;
-                }
-    
-                if purchases.sexy_errors {
-                    member
-                        .add_role(&ctx.http, 884060823205609473)
-                        .await
-                        .into_report()
-                        .attach_printable("Failed to add SexyErrors role")
-                        .change_context(CommandRuntimeError)?;
-                }
-    
-    
-                "Your roles have been assigned".to_string()
-            },
+            Some(_) => format!("Your roles have been updated\n{}", diff.describe()),
+            None if diff.has_changes() => format!(
+                "**You are not linked.** Your support roles have been removed, link your account at <https://leystryku.support/> to get them back.\n{}",
+                diff.describe()
+            ),
             None => "**You are not linked.** Linking your account at <https://leystryku.support/> is required before you can receive support roles.".to_string()
         };
 
-        command
-            .create_interaction_response(&ctx.http, |response| {
-                response
-                    .kind(InteractionResponseType::ChannelMessageWithSource)
-                    .interaction_response_data(|message| {
-                        message.ephemeral(true).content(interaction_response)
-                    })
-            })
-            .await
-            .into_report()
-            .attach_printable("Failed to send interaction response")
-            .change_context(CommandRuntimeError)?;
+        Ok(interaction_response.into())
+    }
+
+    const DEFER: bool = true;
 
-        Ok(())
+    fn name() -> &'static str {
+        "roles"
     }
 
     fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
         command
-            .name("roles")
             .description("Get access to the support channels")
             .dm_permission(false)
     }
@@ -1,15 +1,12 @@
-use super::CommandRuntimeError;
+use super::{CommandOptions, CommandResponse, CommandRuntimeError};
+use crate::steamid::SteamId;
 use async_trait::async_trait;
-use error_stack::{IntoReport, Report, Result, ResultExt};
+use error_stack::{IntoReport, Result, ResultExt};
 use serenity::{
-    builder::CreateApplicationCommand,
+    builder::{CreateApplicationCommand, CreateEmbed},
     client::Context,
     model::application::{
-        command::CommandOptionType,
-        interaction::{
-            application_command::{ApplicationCommandInteraction, CommandDataOptionValue},
-            InteractionResponseType,
-        },
+        command::CommandOptionType, interaction::application_command::ApplicationCommandInteraction,
     },
 };
 
@@ -19,27 +16,12 @@ pub struct SteamCommand;
 impl super::Command for SteamCommand {
     async fn execute(
         handler: &crate::Handler,
-        command: &mut ApplicationCommandInteraction,
-        ctx: Context,
-    ) -> Result<(), CommandRuntimeError> {
-        let target = match command.data.options.get(0) {
-            Some(target) => match target.resolved.as_ref() {
-                Some(target) => target,
-                None => {
-                    return Err(Report::new(CommandRuntimeError)
-                        .attach_printable("Failed to parse command target as a user"));
-                }
-            },
-            None => {
-                return Err(Report::new(CommandRuntimeError)
-                    .attach_printable("Failed to get command target"));
-            }
-        };
-
-        let CommandDataOptionValue::User(user, _member) = target else {
-            return Err(Report::new(CommandRuntimeError)
-                .attach_printable("Failed to fetch and validate user from command target"));
-        };
+        command: &ApplicationCommandInteraction,
+        _ctx: Context,
+    ) -> Result<CommandResponse, CommandRuntimeError> {
+        let user = CommandOptions::new(command)
+            .user("user")
+            .change_context(CommandRuntimeError)?;
 
         let api_response = handler
             .http
@@ -48,31 +30,37 @@ impl super::Command for SteamCommand {
             .await
             .change_context(CommandRuntimeError)?;
 
-        let interaction_reply = match api_response {
-            Some(response) => format!("https://steamcommunity.com/profiles/{}", response.steam_id),
-            None => "User is not linked.".to_string(),
+        let Some(api_user) = api_response else {
+            return Ok("User is not linked.".into());
         };
 
-        command
-            .create_interaction_response(&ctx.http, |response| {
-                response
-                    .kind(InteractionResponseType::ChannelMessageWithSource)
-                    .interaction_response_data(|message| {
-                        message.ephemeral(true).content(interaction_reply)
-                    })
-            })
-            .await
+        let steam_id = SteamId::from_u64(api_user.steam_id)
             .into_report()
-            .attach_printable("Failed to send interaction response")
+            .attach_printable_lazy(|| {
+                format!("Linked Steam account is invalid: {}", api_user.steam_id)
+            })
             .change_context(CommandRuntimeError)?;
 
-        Ok(())
+        let mut embed = CreateEmbed::default();
+        embed
+            .title(format!("{}'s Steam Account", user.name))
+            .url(steam_id.profile_url())
+            .field("SteamID64", format!("`{}`", steam_id), false)
+            .field("SteamID", format!("`{}`", steam_id.steam2()), false)
+            .field("SteamID3", format!("`{}`", steam_id.steam3()), false)
+            .field("Profile", steam_id.profile_url(), false)
+            .colour(serenity::utils::Colour::from(0xBF8AE0));
+
+        Ok(embed.into())
+    }
+
+    fn name() -> &'static str {
+        "steam"
     }
 
     fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
         command
-            .name("steam")
-            .description("Retrieve user Steam account page.")
+            .description("Retrieve user Steam account page and IDs.")
             .create_option(|option| {
                 option
                     .name("user")
@@ -1,16 +1,14 @@
-use super::CommandRuntimeError;
+use super::{CommandOptions, CommandResponse, CommandRuntimeError};
+use crate::http::ApiError;
 use async_trait::async_trait;
-use error_stack::{IntoReport, Report, Result, ResultExt};
+use error_stack::{IntoReport, Result, ResultExt};
 use serenity::{
     builder::CreateApplicationCommand,
     client::Context,
     model::{
         application::{
             command::CommandOptionType,
-            interaction::{
-                application_command::{ApplicationCommandInteraction, CommandDataOptionValue},
-                InteractionResponseType,
-            },
+            interaction::application_command::ApplicationCommandInteraction,
         },
         mention::Mention,
         permissions::Permissions,
@@ -23,27 +21,12 @@ pub struct UnlinkCommand;
 impl super::Command for UnlinkCommand {
     async fn execute(
         handler: &crate::Handler,
-        command: &mut ApplicationCommandInteraction,
+        command: &ApplicationCommandInteraction,
         ctx: Context,
-    ) -> Result<(), CommandRuntimeError> {
-        let target = match command.data.options.get(0) {
-            Some(target) => match target.resolved.as_ref() {
-                Some(target) => target,
-                None => {
-                    return Err(Report::new(CommandRuntimeError)
-                        .attach_printable("Failed to parse command target as a user"));
-                }
-            },
-            None => {
-                return Err(Report::new(CommandRuntimeError)
-                    .attach_printable("Failed to get command target"));
-            }
-        };
-
-        let CommandDataOptionValue::User(user, _member) = target else {
-            return Err(Report::new(CommandRuntimeError)
-                .attach_printable("Failed to fetch and validate user from command target"));
-        };
+    ) -> Result<CommandResponse, CommandRuntimeError> {
+        let (user, member) = CommandOptions::new(command)
+            .user_with_member("user")
+            .change_context(CommandRuntimeError)?;
 
         let api_response = handler
             .http
@@ -54,34 +37,49 @@ impl super::Command for UnlinkCommand {
 
         let interaction_reply = match api_response {
             Some(api_user) => {
-                api_user
-                    .delete()
-                    .await
-                    .change_context(CommandRuntimeError)?;
-                format!("Unlinked {}", Mention::User(user.id))
+                match api_user.delete().await {
+                    Ok(()) => {}
+                    // Someone else got there first, the roles still need tidying up.
+                    Err(e) if matches!(e.downcast_ref::<ApiError>(), Some(ApiError::NotFound)) => {
+                        debug!("{} was already unlinked", user.tag());
+                    }
+                    Err(e) => return Err(e.change_context(CommandRuntimeError)),
+                }
+
+                // Members who already left the guild have no roles to revoke.
+                match (member, command.guild_id) {
+                    (Some(_), Some(guild_id)) => {
+                        let mut member = guild_id
+                            .member(&ctx.http, user.id)
+                            .await
+                            .into_report()
+                            .attach_printable("Failed to fetch member from command target")
+                            .change_context(CommandRuntimeError)?;
+
+                        let diff =
+                            crate::roles::reconcile(&ctx.http, &mut member, &handler.config, None)
+                                .await
+                                .change_context(CommandRuntimeError)?;
+
+                        format!("Unlinked {}\n{}", Mention::User(user.id), diff.describe())
+                    }
+                    _ => format!("Unlinked {}", Mention::User(user.id)),
+                }
             }
             None => format!("{} is not linked.", Mention::User(user.id)),
         };
 
-        command
-            .create_interaction_response(&ctx.http, |response| {
-                response
-                    .kind(InteractionResponseType::ChannelMessageWithSource)
-                    .interaction_response_data(|message| {
-                        message.ephemeral(true).content(interaction_reply)
-                    })
-            })
-            .await
-            .into_report()
-            .attach_printable("Failed to send interaction response")
-            .change_context(CommandRuntimeError)?;
+        Ok(interaction_reply.into())
+    }
+
+    const AUDITED: bool = true;
 
-        Ok(())
+    fn name() -> &'static str {
+        "unlink"
     }
 
     fn register(command: &mut CreateApplicationCommand) -> &mut CreateApplicationCommand {
         command
-            .name("unlink")
             .description("Unlink user account")
             .create_option(|option| {
                 option
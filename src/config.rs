@@ -0,0 +1,510 @@
+use chrono::{DateTime, Utc};
+use error_stack::{Context, IntoReport, Report, Result, ResultExt};
+use serde::Deserialize;
+use serenity::model::id::{ChannelId, GuildId, RoleId};
+use std::{
+    collections::{HashMap, HashSet},
+    net::SocketAddr,
+};
+
+#[derive(Debug)]
+pub struct ConfigError;
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt.write_str("Config Error: An error occurred while loading the config file")
+    }
+}
+
+impl Context for ConfigError {}
+
+#[derive(Deserialize, Debug)]
+pub struct Config {
+    pub roles: RolesConfig,
+    #[serde(default)]
+    pub products: Vec<ProductConfig>,
+    #[serde(default)]
+    pub sync: SyncConfig,
+    #[serde(default)]
+    pub welcome: WelcomeConfig,
+    #[serde(default)]
+    pub cache: CacheConfig,
+    #[serde(default)]
+    pub http: HttpConfig,
+    #[serde(default)]
+    pub campaigns: Vec<CampaignConfig>,
+    #[serde(default)]
+    pub store: StoreConfig,
+    #[serde(default)]
+    pub audit: AuditConfig,
+    #[serde(default)]
+    pub server: ServerConfig,
+    #[serde(default)]
+    pub monitoring: MonitoringConfig,
+    #[serde(default)]
+    pub announcements: AnnouncementConfig,
+    #[serde(default)]
+    pub shutdown: ShutdownConfig,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RolesConfig {
+    /// Role given to every member with a linked account.
+    pub verified: RoleId,
+}
+
+/// A product in the catalog, listed in the order it is displayed.
+#[derive(Deserialize, Debug)]
+pub struct ProductConfig {
+    /// Identifier used by the linking site's purchases endpoint.
+    pub key: String,
+    /// Display name shown to users.
+    pub name: String,
+    /// GmodStore product UUID, required for coupons.
+    pub gmodstore_id: Option<String>,
+    /// Support role granted to owners.
+    pub role: Option<RoleId>,
+    pub emoji: Option<String>,
+}
+
+impl ProductConfig {
+    pub fn label(&self) -> String {
+        match &self.emoji {
+            Some(emoji) => format!("{} {}", emoji, self.name),
+            None => self.name.clone(),
+        }
+    }
+}
+
+/// A coupon offer members can claim with `/coupon`.
+#[derive(Deserialize, Debug)]
+pub struct CampaignConfig {
+    /// Identifier users pick the campaign by.
+    pub key: String,
+    pub name: String,
+    /// Catalog key of the discounted product, it must have a `gmodstore_id`.
+    pub product: String,
+    pub percent: u8,
+    /// Uses per coupon.
+    #[serde(default = "default_one")]
+    pub max_uses: u8,
+    /// Days each coupon stays valid for.
+    pub lifetime_days: u64,
+    pub starts_at: Option<DateTime<Utc>>,
+    pub ends_at: Option<DateTime<Utc>>,
+    /// Coupons a single user may ever claim from this campaign.
+    #[serde(default = "default_one")]
+    pub per_user_limit: u8,
+    /// Prepended to generated codes so coupons can be traced back to the campaign.
+    pub code_prefix: Option<String>,
+    #[serde(default)]
+    pub eligibility: EligibilityConfig,
+}
+
+impl CampaignConfig {
+    pub fn code_prefix(&self) -> String {
+        self.code_prefix
+            .clone()
+            .unwrap_or_else(|| self.key.to_uppercase())
+    }
+
+    pub fn is_running(&self, now: DateTime<Utc>) -> bool {
+        self.starts_at.is_none_or(|start| start <= now) && self.ends_at.is_none_or(|end| now < end)
+    }
+}
+
+/// Rules over the catalog keys of the products a user owns.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct EligibilityConfig {
+    /// Every one of these must be owned.
+    pub requires_all: Vec<String>,
+    /// At least one of these must be owned, ignored when empty.
+    pub requires_any: Vec<String>,
+    /// None of these may be owned.
+    pub excludes: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct SyncConfig {
+    /// Off unless asked for, a run strips managed roles from every unlinked member.
+    pub enabled: bool,
+    /// Seconds between two guild wide role syncs.
+    pub interval: u64,
+    /// How many members are reconciled at the same time.
+    pub concurrency: usize,
+    /// Staff channel the summary of each run is posted to.
+    pub log_channel: Option<ChannelId>,
+}
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval: 60 * 60,
+            concurrency: 4,
+            log_channel: None,
+        }
+    }
+}
+
+/// Link API cache lifetimes in seconds, 0 disables caching.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct CacheConfig {
+    pub user_ttl: u64,
+    pub purchases_ttl: u64,
+    /// How long an unlinked (404) lookup is remembered.
+    pub not_found_ttl: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            user_ttl: 5 * 60,
+            purchases_ttl: 60,
+            not_found_ttl: 60,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct ServerConfig {
+    /// Serve webhooks, each one also needs its secret set in the environment.
+    pub enabled: bool,
+    pub bind: SocketAddr,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind: SocketAddr::from(([127, 0, 0, 1], 8080)),
+        }
+    }
+}
+
+/// Metrics and health checks are served on their own listener, so they don't
+/// depend on webhooks being enabled and can stay off the public address.
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct MonitoringConfig {
+    /// Nothing is served while this is unset.
+    pub bind: Option<SocketAddr>,
+    /// Seconds the gateway may be away before the liveness check fails.
+    pub unhealthy_after: u64,
+}
+
+impl Default for MonitoringConfig {
+    fn default() -> Self {
+        Self {
+            bind: None,
+            unhealthy_after: 5 * 60,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct ShutdownConfig {
+    /// Seconds to wait for running commands and sync jobs after SIGTERM or SIGINT.
+    pub grace_period: u64,
+}
+
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        Self { grace_period: 30 }
+    }
+}
+
+/// Purchases reported by GmodStore webhooks are announced here.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct AnnouncementConfig {
+    pub channel: Option<ChannelId>,
+    /// Leave the buyer out, otherwise linked members in the guild are mentioned.
+    pub anonymous: bool,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct AuditConfig {
+    /// Staff channel every privileged command is posted to, entries are stored regardless.
+    pub channel: Option<ChannelId>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct StoreConfig {
+    /// SQLite database file, `:memory:` keeps everything in memory and loses it on exit.
+    pub path: String,
+}
+
+impl Default for StoreConfig {
+    fn default() -> Self {
+        Self {
+            path: "leybot.db".to_string(),
+        }
+    }
+}
+
+/// Timeouts and retry behaviour shared by the link API and GmodStore clients.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct HttpConfig {
+    /// Seconds allowed to establish a connection.
+    pub connect_timeout: u64,
+    /// Seconds allowed for a whole request, including the response body.
+    pub request_timeout: u64,
+    /// Extra attempts made for idempotent requests, 0 disables retries.
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled for each one after it.
+    pub backoff_base_ms: u64,
+    /// Upper bound on a single backoff, longer `Retry-After` delays are not waited out.
+    pub backoff_max_ms: u64,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout: 5,
+            request_timeout: 15,
+            max_retries: 3,
+            backoff_base_ms: 250,
+            backoff_max_ms: 10_000,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct WelcomeConfig {
+    /// Template used for guilds without an entry in `guilds`.
+    pub default: Option<WelcomeTemplate>,
+    /// Per guild templates, keyed by guild ID.
+    #[serde(default)]
+    pub guilds: HashMap<String, WelcomeTemplate>,
+}
+
+impl WelcomeConfig {
+    pub fn template(&self, guild_id: GuildId) -> Option<&WelcomeTemplate> {
+        self.guilds
+            .get(&guild_id.to_string())
+            .or(self.default.as_ref())
+            .filter(|template| template.enabled)
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WelcomeDelivery {
+    #[default]
+    Channel,
+    Dm,
+}
+
+/// A welcome message, every text field may contain template placeholders.
+#[derive(Deserialize, Debug)]
+pub struct WelcomeTemplate {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub delivery: WelcomeDelivery,
+    /// Required when `delivery` is `channel`.
+    pub channel: Option<ChannelId>,
+    /// Plain message content, sent above the embed.
+    pub content: Option<String>,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub colour: Option<u32>,
+    #[serde(default)]
+    pub fields: Vec<WelcomeField>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct WelcomeField {
+    pub name: String,
+    pub value: String,
+    #[serde(default)]
+    pub inline: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_one() -> u8 {
+    1
+}
+
+impl Config {
+    pub fn load() -> Result<Self, ConfigError> {
+        let path = crate::misc::get_env("CONFIG_PATH").unwrap_or_else(|_| "config.toml".into());
+
+        let contents = std::fs::read_to_string(&path)
+            .into_report()
+            .attach_printable_lazy(|| format!("Failed to read config file: {}", path))
+            .change_context(ConfigError)?;
+
+        let config = toml::from_str::<Config>(&contents)
+            .into_report()
+            .attach_printable_lazy(|| format!("Failed to parse config file: {}", path))
+            .change_context(ConfigError)?;
+
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    pub fn product(&self, key: &str) -> Option<&ProductConfig> {
+        self.products.iter().find(|product| product.key == key)
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        let mut keys = HashSet::new();
+        for product in &self.products {
+            if !keys.insert(product.key.as_str()) {
+                return Err(Report::new(ConfigError).attach_printable(format!(
+                    "Duplicate product key in [[products]]: {}",
+                    product.key
+                )));
+            }
+        }
+
+        if self.sync.interval == 0 {
+            return Err(
+                Report::new(ConfigError).attach_printable("[sync] interval must be greater than 0")
+            );
+        }
+
+        if self.sync.concurrency == 0 {
+            return Err(Report::new(ConfigError)
+                .attach_printable("[sync] concurrency must be greater than 0"));
+        }
+
+        if self.http.connect_timeout == 0 || self.http.request_timeout == 0 {
+            return Err(
+                Report::new(ConfigError).attach_printable("[http] timeouts must be greater than 0")
+            );
+        }
+
+        self.validate_campaigns()?;
+
+        let welcome_guilds = self.welcome.guilds.iter().map(|(guild, template)| {
+            (
+                format!("[welcome.guilds.{}]", guild),
+                guild.parse::<u64>().is_ok(),
+                template,
+            )
+        });
+        let welcome_default = self
+            .welcome
+            .default
+            .iter()
+            .map(|template| ("[welcome.default]".to_string(), true, template));
+
+        for (section, valid_key, template) in welcome_default.chain(welcome_guilds) {
+            if !valid_key {
+                return Err(Report::new(ConfigError)
+                    .attach_printable(format!("{} is not keyed by a guild ID", section)));
+            }
+
+            if template.enabled
+                && template.delivery == WelcomeDelivery::Channel
+                && template.channel.is_none()
+            {
+                return Err(Report::new(ConfigError).attach_printable(format!(
+                    "{} is delivered to a channel but has no channel set",
+                    section
+                )));
+            }
+
+            // Discord refuses messages with neither, and fields alone don't make an embed.
+            let has_text =
+                |text: &Option<String>| text.as_ref().is_some_and(|t| !t.trim().is_empty());
+            if template.enabled
+                && !has_text(&template.content)
+                && !has_text(&template.title)
+                && !has_text(&template.description)
+            {
+                return Err(Report::new(ConfigError).attach_printable(format!(
+                    "{} needs content, a title or a description",
+                    section
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn validate_campaigns(&self) -> Result<(), ConfigError> {
+        let mut keys = HashSet::new();
+        for campaign in &self.campaigns {
+            let invalid = |reason: String| {
+                Err(Report::new(ConfigError)
+                    .attach_printable(format!("[[campaigns]] {}: {}", campaign.key, reason)))
+            };
+
+            if !keys.insert(campaign.key.to_lowercase()) {
+                return invalid("duplicate campaign key".into());
+            }
+
+            match self.product(&campaign.product) {
+                Some(product) if product.gmodstore_id.is_some() => {}
+                Some(_) => return invalid(format!("{} has no gmodstore_id", campaign.product)),
+                None => return invalid(format!("unknown product {}", campaign.product)),
+            }
+
+            let rules = &campaign.eligibility;
+            let unknown = rules
+                .requires_all
+                .iter()
+                .chain(&rules.requires_any)
+                .chain(&rules.excludes)
+                .find(|key| self.product(key).is_none());
+            if let Some(key) = unknown {
+                return invalid(format!("eligibility refers to unknown product {}", key));
+            }
+
+            if campaign.percent == 0 || campaign.percent > 90 {
+                return invalid("percent must be between 1 and 90".into());
+            }
+            if campaign.max_uses == 0 || campaign.max_uses > 100 {
+                return invalid("max_uses must be between 1 and 100".into());
+            }
+            if campaign.lifetime_days == 0 || campaign.lifetime_days > 365 {
+                return invalid("lifetime_days must be between 1 and 365".into());
+            }
+            if campaign.per_user_limit == 0 {
+                return invalid("per_user_limit must be greater than 0".into());
+            }
+            if let (Some(start), Some(end)) = (campaign.starts_at, campaign.ends_at) {
+                if start >= end {
+                    return invalid("starts_at must be before ends_at".into());
+                }
+            }
+            // Leaves room for the separator and a 24 character cuid.
+            if campaign.code_prefix().len() > 32 {
+                return invalid("code_prefix must be at most 32 characters".into());
+            }
+        }
+
+        // Coupons are told apart by their code prefix, so no campaign's codes may
+        // also start with another's.
+        for (index, campaign) in self.campaigns.iter().enumerate() {
+            let prefix = format!("{}-", campaign.code_prefix());
+            let overlapping = self.campaigns[index + 1..].iter().find(|other| {
+                let other_prefix = format!("{}-", other.code_prefix());
+                prefix.starts_with(&other_prefix) || other_prefix.starts_with(&prefix)
+            });
+            if let Some(other) = overlapping {
+                return Err(Report::new(ConfigError).attach_printable(format!(
+                    "[[campaigns]] {}: code_prefix overlaps with {}",
+                    campaign.key, other.key
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
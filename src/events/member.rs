@@ -1,8 +1,10 @@
-use error_stack::{Context as ErrorContext, IntoReport, Result, ResultExt};
+use error_stack::{Context as ErrorContext, Result, ResultExt};
+use serenity::builder::CreateMessage;
 use serenity::client::Context;
 use serenity::model::guild::Member;
-use serenity::model::id::ChannelId;
-use serenity::model::mention::Mention;
+
+use crate::config::{WelcomeDelivery, WelcomeTemplate};
+use crate::template::TemplateContext;
 
 #[derive(Debug)]
 pub struct MemberCreateEventRuntimeError;
@@ -20,58 +22,30 @@ pub async fn member_create(
     ctx: Context,
     mut new_member: Member,
 ) -> Result<(), MemberCreateEventRuntimeError> {
-    let guild_channels = new_member
-        .guild_id
-        .channels(&ctx.http)
-        .await
-        .into_report()
-        .attach_printable("Failed to fetch guild from member creation event")
-        .change_context(MemberCreateEventRuntimeError)?;
-
-    let welcome_channel_id = ChannelId(884064278112522260);
-
-    let channel = guild_channels.get(&welcome_channel_id);
-
-    if let Some(channel) = channel {
-        channel
-            .send_message(&ctx.http, |message| {
-                message
-                    .add_embed(|embed| {
-                        embed
-                            .title("Welcome")
-                            .description(format!(
-                                "Welcome to the support server for Leystryku's GmodStore addons.
-                If you are not already verified please read {}",
-                                Mention::Channel(ChannelId(884069163306479647))
-                            ))
-                            .field(
-                                "**Please remember to read the rules**",
-                                Mention::Channel(ChannelId(884050630241550376)),
-                                false,
-                            )
-                            .colour(serenity::utils::Colour::from(0x85F2F2))
-                    })
-                    .content(Mention::User(new_member.user.id))
-            })
-            .await
-            .into_report()
-            .attach_printable("Failed to send message to welcome channel")
-            .change_context(MemberCreateEventRuntimeError)?;
-    }
-
+    // An outage shouldn't cost anyone their welcome, only the verified role.
     let user = handler
         .http
         .link_client
         .get_user_by_discord(new_member.user.id.0)
         .await
-        .change_context(MemberCreateEventRuntimeError)?;
+        .change_context(MemberCreateEventRuntimeError);
+
+    if let Some(template) = handler.config.welcome.template(new_member.guild_id) {
+        let template_ctx = TemplateContext {
+            user: &new_member.user,
+            member_count: ctx
+                .cache
+                .guild_field(new_member.guild_id, |guild| guild.member_count),
+            linked: user.as_ref().ok().map(Option::is_some),
+        };
+
+        send_welcome(&ctx, &new_member, template, &template_ctx).await;
+    }
 
-    match user {
+    match user? {
         Some(_) => {
-            new_member
-                .add_role(&ctx.http, 884063960582721597)
+            crate::roles::grant_verified_role(&ctx.http, &mut new_member, &handler.config.roles)
                 .await
-                .into_report()
                 .attach_printable_lazy(|| {
                     format!("Failed to add verified role for {}", new_member.user.tag())
                 })
@@ -82,3 +56,74 @@ pub async fn member_create(
         None => Ok(()),
     }
 }
+
+async fn send_welcome(
+    ctx: &Context,
+    member: &Member,
+    template: &WelcomeTemplate,
+    template_ctx: &TemplateContext<'_>,
+) {
+    let build = |message: &mut CreateMessage| {
+        if let Some(content) = &template.content {
+            message.content(template_ctx.render(content));
+        }
+
+        if template.title.is_some() || template.description.is_some() {
+            message.add_embed(|embed| {
+                if let Some(title) = &template.title {
+                    embed.title(template_ctx.render(title));
+                }
+                if let Some(description) = &template.description {
+                    embed.description(template_ctx.render(description));
+                }
+                for field in &template.fields {
+                    embed.field(
+                        template_ctx.render(&field.name),
+                        template_ctx.render(&field.value),
+                        field.inline,
+                    );
+                }
+                if let Some(colour) = template.colour {
+                    embed.colour(serenity::utils::Colour::from(colour));
+                }
+                embed
+            });
+        }
+    };
+
+    match (template.delivery, template.channel) {
+        (WelcomeDelivery::Dm, _) => {
+            // Members with closed DMs are common, this shouldn't stop the role assignment.
+            if let Err(e) = member
+                .user
+                .direct_message(&ctx.http, |message| {
+                    build(message);
+                    message
+                })
+                .await
+            {
+                warn!("Failed to send welcome DM to {}: {}", member.user.tag(), e);
+            }
+        }
+        (WelcomeDelivery::Channel, Some(channel)) => {
+            // Nor should a deleted channel or missing permission.
+            if let Err(e) = channel
+                .send_message(&ctx.http, |message| {
+                    build(message);
+                    message
+                })
+                .await
+            {
+                warn!(
+                    "Failed to send welcome message for {} to {}: {}",
+                    member.user.tag(),
+                    channel,
+                    e
+                );
+            }
+        }
+        (WelcomeDelivery::Channel, None) => {
+            warn!("Welcome template has no channel set, skipping welcome message");
+        }
+    }
+}
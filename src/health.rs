@@ -0,0 +1,58 @@
+use serde::Serialize;
+use serenity::gateway::ConnectionStage;
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Gateway connection state, as far as health checks are concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GatewayState {
+    Connecting,
+    Connected,
+    Disconnected,
+}
+
+impl From<ConnectionStage> for GatewayState {
+    fn from(stage: ConnectionStage) -> Self {
+        match stage {
+            ConnectionStage::Connected => Self::Connected,
+            ConnectionStage::Disconnected => Self::Disconnected,
+            _ => Self::Connecting,
+        }
+    }
+}
+
+/// Runtime state reported by the health endpoints, updated by the event handler.
+pub struct Health {
+    gateway: Mutex<(GatewayState, Instant)>,
+}
+
+impl Health {
+    pub fn new() -> Self {
+        Self {
+            gateway: Mutex::new((GatewayState::Connecting, Instant::now())),
+        }
+    }
+
+    pub fn set_gateway(&self, state: GatewayState) {
+        let mut gateway = self.gateway.lock().unwrap();
+        if gateway.0 != state {
+            debug!("Gateway is now {:?}", state);
+            *gateway = (state, Instant::now());
+        }
+    }
+
+    /// The current gateway state and how long it has been in it.
+    pub fn gateway(&self) -> (GatewayState, Duration) {
+        let (state, since) = *self.gateway.lock().unwrap();
+        (state, since.elapsed())
+    }
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self::new()
+    }
+}
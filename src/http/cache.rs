@@ -0,0 +1,177 @@
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use super::{ApiPurchaseObject, ApiUserObject};
+use crate::config::CacheConfig;
+
+/// Expired entries are only swept once a cache grows past this size.
+const PRUNE_THRESHOLD: usize = 1024;
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+}
+
+impl CacheStats {
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            return 0.0;
+        }
+        self.hits as f64 / total as f64
+    }
+}
+
+/// A map whose entries expire after a per-entry time to live.
+pub struct TtlCache<K, V> {
+    /// The `cache` label of its metrics.
+    name: &'static str,
+    entries: Mutex<HashMap<K, (Instant, V)>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl<K: Eq + Hash, V: Clone> TtlCache<K, V> {
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            entries: Mutex::new(HashMap::new()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        let mut entries = self.entries.lock().unwrap();
+
+        let value = match entries.get(key) {
+            Some((expires, value)) if *expires > Instant::now() => Some(value.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        };
+
+        let result = match value {
+            Some(_) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                "hit"
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                "miss"
+            }
+        };
+        crate::metrics::metrics()
+            .cache_requests
+            .with_label_values(&[self.name, result])
+            .inc();
+
+        value
+    }
+
+    /// Stores a value, a zero TTL disables caching for it.
+    pub fn insert(&self, key: K, value: V, ttl: Duration) {
+        if ttl.is_zero() {
+            return;
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        let now = Instant::now();
+
+        if entries.len() >= PRUNE_THRESHOLD {
+            entries.retain(|_, (expires, _)| *expires > now);
+        }
+
+        entries.insert(key, (now + ttl, value));
+    }
+
+    pub fn remove(&self, key: &K) {
+        self.entries.lock().unwrap().remove(key);
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: self.entries.lock().unwrap().len(),
+        }
+    }
+}
+
+/// Caches link API lookups.
+///
+/// Users are keyed by Discord ID, `None` recording that the API returned 404.
+/// Purchases are keyed by the user's UUID.
+pub struct LinkCache {
+    users: TtlCache<u64, Option<ApiUserObject>>,
+    purchases: TtlCache<String, ApiPurchaseObject>,
+    config: CacheConfig,
+}
+
+impl LinkCache {
+    pub fn new(config: CacheConfig) -> Self {
+        Self {
+            users: TtlCache::new("users"),
+            purchases: TtlCache::new("purchases"),
+            config,
+        }
+    }
+
+    pub fn get_user(&self, discord_id: u64) -> Option<Option<ApiUserObject>> {
+        self.users.get(&discord_id)
+    }
+
+    pub fn insert_user(&self, discord_id: u64, user: Option<ApiUserObject>) {
+        let ttl = match user {
+            Some(_) => self.config.user_ttl,
+            None => self.config.not_found_ttl,
+        };
+        self.users
+            .insert(discord_id, user, Duration::from_secs(ttl));
+    }
+
+    pub fn get_purchases(&self, uuid: &str) -> Option<ApiPurchaseObject> {
+        self.purchases.get(&uuid.to_string())
+    }
+
+    pub fn insert_purchases(&self, uuid: &str, purchases: ApiPurchaseObject) {
+        self.purchases.insert(
+            uuid.to_string(),
+            purchases,
+            Duration::from_secs(self.config.purchases_ttl),
+        );
+    }
+
+    /// Forgets everything cached for a Discord user, e.g. after they (un)link.
+    pub fn invalidate_discord(&self, discord_id: u64) {
+        let removed = self.users.entries.lock().unwrap().remove(&discord_id);
+
+        if let Some((_, Some(user))) = removed {
+            self.purchases.remove(&user.uuid);
+        }
+    }
+
+    /// Forgets the purchases cached for a linked user.
+    pub fn invalidate_purchases(&self, uuid: &str) {
+        self.purchases.remove(&uuid.to_string());
+    }
+
+    pub fn user_stats(&self) -> CacheStats {
+        self.users.stats()
+    }
+
+    pub fn purchase_stats(&self) -> CacheStats {
+        self.purchases.stats()
+    }
+}
@@ -0,0 +1,111 @@
+use error_stack::{Context, Report};
+use reqwest::{Response, StatusCode};
+use serde::Deserialize;
+
+/// Longest API message body kept on a validation error.
+const MESSAGE_LIMIT: usize = 300;
+
+/// Why an upstream API request failed.
+///
+/// Always found beneath `LinkClientHTTPError` or `GMSClientHTTPError` in a
+/// report, retrieve it with `report.downcast_ref::<ApiError>()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApiError {
+    /// The request never got a response, e.g. a timeout or refused connection.
+    Unreachable,
+    /// Our credentials were missing, invalid or lack access (401/403).
+    Unauthorized,
+    NotFound,
+    /// Still rate limited after retrying, with the `Retry-After` delay in seconds.
+    RateLimited {
+        retry_after: Option<u64>,
+    },
+    /// The request was rejected (400/422), with the message from the API.
+    Validation(String),
+    Server(StatusCode),
+    /// Any other status we don't know how to handle.
+    Unexpected(StatusCode),
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unreachable => fmt.write_str("API Error: The API could not be reached"),
+            Self::Unauthorized => fmt.write_str("API Error: The API rejected our credentials"),
+            Self::NotFound => fmt.write_str("API Error: The requested resource does not exist"),
+            Self::RateLimited {
+                retry_after: Some(seconds),
+            } => write!(fmt, "API Error: Rate limited, retry after {}s", seconds),
+            Self::RateLimited { retry_after: None } => fmt.write_str("API Error: Rate limited"),
+            Self::Validation(message) => write!(fmt, "API Error: Request rejected: {}", message),
+            Self::Server(status) => write!(fmt, "API Error: Server error ({})", status),
+            Self::Unexpected(status) => write!(fmt, "API Error: Unexpected status ({})", status),
+        }
+    }
+}
+
+impl Context for ApiError {}
+
+/// The shape of error bodies returned by both APIs.
+#[derive(Deserialize)]
+struct ErrorBody {
+    message: Option<String>,
+    error: Option<String>,
+}
+
+impl ApiError {
+    fn from_status(status: StatusCode, body: &str, retry_after: Option<u64>) -> Self {
+        match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Self::Unauthorized,
+            StatusCode::NOT_FOUND => Self::NotFound,
+            StatusCode::TOO_MANY_REQUESTS => Self::RateLimited { retry_after },
+            StatusCode::BAD_REQUEST | StatusCode::UNPROCESSABLE_ENTITY => {
+                Self::Validation(Self::message(body))
+            }
+            status if status.is_server_error() => Self::Server(status),
+            status => Self::Unexpected(status),
+        }
+    }
+
+    /// Pulls a readable message out of an error body, falling back to the raw text.
+    fn message(body: &str) -> String {
+        let message = serde_json::from_str::<ErrorBody>(body)
+            .ok()
+            .and_then(|body| body.message.or(body.error))
+            .unwrap_or_else(|| body.trim().to_string());
+
+        if message.is_empty() {
+            return "No details were given".to_string();
+        }
+
+        match message.char_indices().nth(MESSAGE_LIMIT) {
+            Some((end, _)) => format!("{}…", &message[..end]),
+            None => message,
+        }
+    }
+
+    /// Turns an unsuccessful response into an error, reading its body for details.
+    pub async fn check(response: Response) -> Result<Response, Report<Self>> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        let url = response.url().to_string();
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse().ok());
+        let body = response.text().await.unwrap_or_default();
+
+        Err(Report::new(Self::from_status(status, &body, retry_after))
+            .attach_printable(format!("{} returned {}", url, status))
+            .attach_printable(format!("Response body: {}", body)))
+    }
+
+    /// Classifies a request that failed without a usable response.
+    pub fn from_transport(report: Report<reqwest::Error>) -> Report<Self> {
+        report.change_context(Self::Unreachable)
+    }
+}
@@ -0,0 +1,745 @@
+use chrono::{DateTime, Days, Utc};
+use error_stack::{Context, IntoReport, Report, Result, ResultExt};
+use futures::{Stream, TryStreamExt};
+use reqwest::Client;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{collections::BTreeMap, time::Duration};
+
+use crate::config::{CacheConfig, HttpConfig};
+
+mod cache;
+mod error;
+mod retry;
+
+pub use cache::{CacheStats, LinkCache};
+pub use error::ApiError;
+use retry::{Idempotency, RetryPolicy};
+
+#[derive(Debug)]
+pub struct HttpClientError;
+
+impl std::fmt::Display for HttpClientError {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt.write_str("HTTP Error: An error occurred while building the HTTP client")
+    }
+}
+
+impl Context for HttpClientError {}
+
+#[derive(Debug)]
+pub struct LinkClientHTTPError;
+
+impl std::fmt::Display for LinkClientHTTPError {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt.write_str("HTTP Error: An error occurred in the Link Client")
+    }
+}
+
+impl Context for LinkClientHTTPError {}
+
+#[derive(Debug)]
+pub struct GMSClientHTTPError;
+
+impl std::fmt::Display for GMSClientHTTPError {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt.write_str("HTTP Error: An error occurred in the Link Client")
+    }
+}
+
+impl Context for GMSClientHTTPError {}
+
+/// A coupon field that failed validation, `reason` reads after the field name.
+#[derive(Debug)]
+pub struct CouponBuilderError {
+    pub field: &'static str,
+    pub reason: &'static str,
+}
+
+impl std::fmt::Display for CouponBuilderError {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            fmt,
+            "An error occurred whilst building the coupon: '{}' {}",
+            self.field, self.reason
+        )
+    }
+}
+
+impl Context for CouponBuilderError {}
+
+pub struct HttpClient {
+    pub link_client: LinkClient,
+    pub gmod_store_client: GmodStoreClient,
+}
+
+impl HttpClient {
+    pub fn new(config: &crate::config::Config) -> Result<Self, HttpClientError> {
+        let link_client = LinkClient::new(config.cache.clone(), &config.http)?;
+        let gmod_store_client = GmodStoreClient::new(&config.http)?;
+        Ok(Self {
+            link_client,
+            gmod_store_client,
+        })
+    }
+}
+
+/// Applies the configured timeouts to a client builder.
+fn client_builder(config: &HttpConfig) -> reqwest::ClientBuilder {
+    reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(config.connect_timeout))
+        .timeout(Duration::from_secs(config.request_timeout))
+}
+
+pub struct LinkClient {
+    client: Client,
+    retry: RetryPolicy,
+    pub url: String,
+    pub cache: LinkCache,
+}
+
+impl LinkClient {
+    pub fn new(cache: CacheConfig, http: &HttpConfig) -> Result<Self, HttpClientError> {
+        let api_key = Self::get_token()?;
+        let api_url = Self::get_url()?;
+
+        let mut api_headers = reqwest::header::HeaderMap::new();
+        api_headers.insert(
+            reqwest::header::AUTHORIZATION,
+            reqwest::header::HeaderValue::from_str(&format!("Bearer {}", api_key))
+                .into_report()
+                .attach_printable_lazy(|| format!("Failed to parse API key: {}", api_key))
+                .change_context(HttpClientError)?,
+        );
+
+        let api_http_builder = client_builder(http).default_headers(api_headers);
+
+        let api_http = api_http_builder
+            .build()
+            .into_report()
+            .attach_printable("Failed to build HTTP client")
+            .change_context(HttpClientError)?;
+        Ok(Self {
+            client: api_http,
+            // 404 is how the link API says someone isn't linked.
+            retry: RetryPolicy::new("link_api", http).not_found_is_success(),
+            url: api_url,
+            cache: LinkCache::new(cache),
+        })
+    }
+
+    /// When the link API last answered without a server error.
+    pub fn last_success(&self) -> Option<DateTime<Utc>> {
+        self.retry.last_success()
+    }
+
+    fn get_url() -> Result<String, HttpClientError> {
+        let url = crate::misc::get_env("API_ENDPOINT")
+            .attach_printable("Failed to read environment variable: API_ENDPOINT")
+            .change_context(HttpClientError)?;
+
+        let url_chars = url.chars().last();
+
+        match url_chars {
+            Some('/') => Err(Report::new(HttpClientError)
+                .attach_printable("API_ENDPOINT ends with a slash ('/') REMOVE IT!")),
+            None => {
+                Err(Report::new(HttpClientError).attach_printable("API_ENDPOINT is not defined"))
+            }
+            _ => Ok(url),
+        }
+    }
+
+    fn get_token() -> Result<String, HttpClientError> {
+        let token = crate::misc::get_env("API_TOKEN")
+            .attach_printable("Failed to read environment variable: API_TOKEN")
+            .change_context(HttpClientError)?;
+        Ok(token)
+    }
+}
+
+/// Whether a GmodStore user or coupon ID is safe to use as a path segment, they are
+/// UUIDs or SteamID64s so only alphanumerics and `-` are allowed.
+pub fn is_gmodstore_id(id: &str) -> bool {
+    !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+/// Items requested per page from GmodStore list endpoints.
+const PER_PAGE: u8 = 100;
+/// Most pages a single list request will follow.
+const MAX_PAGES: usize = 20;
+
+pub struct GmodStoreClient {
+    client: Client,
+    retry: RetryPolicy,
+    url: String,
+}
+
+impl GmodStoreClient {
+    pub fn new(http: &HttpConfig) -> Result<Self, HttpClientError> {
+        let api_key = Self::get_token()?;
+        let api_url = Self::get_url();
+
+        let mut api_headers = reqwest::header::HeaderMap::new();
+        api_headers.insert(
+            reqwest::header::AUTHORIZATION,
+            reqwest::header::HeaderValue::from_str(&format!("Bearer {}", api_key))
+                .into_report()
+                .attach_printable_lazy(|| format!("Failed to parse API key: {}", api_key))
+                .change_context(HttpClientError)?,
+        );
+
+        let api_http_builder = client_builder(http).default_headers(api_headers);
+
+        let api_http = api_http_builder
+            .build()
+            .into_report()
+            .attach_printable("Failed to build GmodStore HTTP client")
+            .change_context(HttpClientError)?;
+        Ok(Self {
+            client: api_http,
+            retry: RetryPolicy::new("gmodstore", http),
+            url: api_url,
+        })
+    }
+
+    /// When GmodStore last answered without a server error.
+    pub fn last_success(&self) -> Option<DateTime<Utc>> {
+        self.retry.last_success()
+    }
+
+    fn get_token() -> Result<String, HttpClientError> {
+        let token = crate::misc::get_env("GMS_PAT")
+            .attach_printable("Failed to read environment variable: GMS_PAT")
+            .change_context(HttpClientError)?;
+        Ok(token)
+    }
+
+    fn get_url() -> String {
+        String::from("https://www.gmodstore.com/api/v3")
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ApiUserResponse {
+    pub data: ApiUserObject,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ApiUserObject {
+    pub uuid: String,
+    pub name: Option<String>,
+    #[serde(rename = "steamId")]
+    pub steam_id: u64,
+    #[serde(rename = "discordId")]
+    pub discord_id: Option<u64>,
+    #[serde(rename = "gmodStoreId")]
+    pub gmod_store_id: Option<String>,
+    pub avatar: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: String,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ApiPurchasesResponse {
+    pub data: ApiPurchaseObject,
+}
+
+/// Ownership of every product known to the linking site, keyed by product identifier.
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+#[serde(transparent)]
+pub struct ApiPurchaseObject(pub BTreeMap<String, bool>);
+
+impl ApiPurchaseObject {
+    pub fn owns(&self, product: &str) -> bool {
+        self.0.get(product).copied().unwrap_or(false)
+    }
+}
+
+pub struct User<'a> {
+    pub uuid: String,
+    pub name: Option<String>,
+    pub steam_id: u64,
+    pub discord_id: Option<u64>,
+    pub gmod_store_id: Option<String>,
+    pub avatar: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+    http: &'a LinkClient,
+}
+
+impl<'a> User<'a> {
+    fn from_api(user_api: ApiUserObject, http: &'a LinkClient) -> Self {
+        Self {
+            uuid: user_api.uuid,
+            name: user_api.name,
+            steam_id: user_api.steam_id,
+            discord_id: user_api.discord_id,
+            gmod_store_id: user_api.gmod_store_id,
+            avatar: user_api.avatar,
+            created_at: user_api.created_at,
+            updated_at: user_api.updated_at,
+            http,
+        }
+    }
+}
+
+impl LinkClient {
+    pub async fn get_user_by_discord(
+        &self,
+        discord_id: u64,
+    ) -> Result<Option<User<'_>>, LinkClientHTTPError> {
+        if let Some(cached) = self.cache.get_user(discord_id) {
+            return Ok(cached.map(|user_api| User::from_api(user_api, self)));
+        }
+
+        let url = format!("{}/api/users/discord/{}", self.url, discord_id);
+        let user_api = self.fetch_user(url).await?;
+
+        self.cache.insert_user(discord_id, user_api.clone());
+
+        Ok(user_api.map(|user_api| User::from_api(user_api, self)))
+    }
+
+    pub async fn get_user_by_steam(
+        &self,
+        steam_id: u64,
+    ) -> Result<Option<User<'_>>, LinkClientHTTPError> {
+        let url = format!("{}/api/users/steam/{}", self.url, steam_id);
+        let user_api = self.fetch_user(url).await?;
+
+        Ok(user_api.map(|user_api| self.remember(user_api)))
+    }
+
+    pub async fn get_user_by_gmodstore(
+        &self,
+        gmod_store_id: &str,
+    ) -> Result<Option<User<'_>>, LinkClientHTTPError> {
+        // It goes into the path, so nothing else may reach the API.
+        if !is_gmodstore_id(gmod_store_id) {
+            debug!(
+                "Refusing to look up malformed GmodStore ID: {}",
+                gmod_store_id
+            );
+            return Ok(None);
+        }
+
+        let url = format!("{}/api/users/gmodstore/{}", self.url, gmod_store_id);
+        let user_api = self.fetch_user(url).await?;
+
+        Ok(user_api.map(|user_api| self.remember(user_api)))
+    }
+
+    /// Caches a user found by another ID, so their next Discord lookup is free.
+    fn remember(&self, user_api: ApiUserObject) -> User<'_> {
+        if let Some(discord_id) = user_api.discord_id {
+            self.cache.insert_user(discord_id, Some(user_api.clone()));
+        }
+        User::from_api(user_api, self)
+    }
+
+    /// Fetches a single user, `None` when the API has no such user.
+    async fn fetch_user(&self, url: String) -> Result<Option<ApiUserObject>, LinkClientHTTPError> {
+        let response = match self
+            .retry
+            .execute(Idempotency::Safe, self.client.get(url))
+            .await
+        {
+            Ok(response) => response,
+            Err(report) if report.current_context() == &ApiError::NotFound => return Ok(None),
+            Err(report) => {
+                return Err(report
+                    .attach_printable("An error occurred while fetching from the API")
+                    .change_context(LinkClientHTTPError))
+            }
+        };
+
+        let user_api = response
+            .json::<ApiUserResponse>()
+            .await
+            .into_report()
+            .attach_printable("An error occurred whilst serializing the API response")
+            .change_context(LinkClientHTTPError)?
+            .data;
+
+        Ok(Some(user_api))
+    }
+}
+
+impl User<'_> {
+    pub async fn get_purchases(&self) -> Result<ApiPurchaseObject, LinkClientHTTPError> {
+        if let Some(cached) = self.http.cache.get_purchases(&self.uuid) {
+            return Ok(cached);
+        }
+
+        let url = format!("{}/api/users/{}/purchases", self.http.url, self.uuid);
+
+        let response = self
+            .http
+            .retry
+            .execute(Idempotency::Safe, self.http.client.get(url))
+            .await
+            .attach_printable("An error occurred while fetching from the API")
+            .change_context(LinkClientHTTPError)?;
+
+        let purchases = response
+            .json::<ApiPurchasesResponse>()
+            .await
+            .into_report()
+            .attach_printable("An error occurred whilst serializing the API response")
+            .change_context(LinkClientHTTPError)?
+            .data;
+
+        self.http
+            .cache
+            .insert_purchases(&self.uuid, purchases.clone());
+
+        Ok(purchases)
+    }
+
+    pub async fn delete(&self) -> Result<(), LinkClientHTTPError> {
+        let url = format!("{}/api/users/{}", self.http.url, self.uuid);
+
+        let result = self
+            .http
+            .retry
+            .execute(Idempotency::Delete, self.http.client.delete(url))
+            .await;
+
+        // Even a failed delete may have gone through, so don't trust the cache either way.
+        if let Some(discord_id) = self.discord_id {
+            self.http.cache.invalidate_discord(discord_id);
+        }
+        self.http.cache.invalidate_purchases(&self.uuid);
+
+        result
+            .attach_printable("Failed to send delete request to API")
+            .change_context(LinkClientHTTPError)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct GMSCursorsObject {
+    pub previous: Option<String>,
+    pub next: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct GMSMetaObject {
+    #[serde(rename = "perPage")]
+    pub per_age: u8,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct GMSCouponObject {
+    pub id: String,
+    pub code: String,
+    pub percent: u8,
+    #[serde(rename = "maxUses")]
+    pub max_uses: u8,
+    #[serde(rename = "boundUser")]
+    pub bound_user: Option<String>,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: String,
+}
+
+/// One page of a GmodStore list endpoint.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct GMSListResponse<T> {
+    pub data: Vec<T>,
+    #[serde(default)]
+    pub connections: Vec<String>,
+    pub cursors: GMSCursorsObject,
+    pub meta: Option<GMSMetaObject>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct GMSCouponResponse {
+    pub data: GMSCouponObject,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct CouponBuilder {
+    pub code: String,
+    pub percent: u8,
+    #[serde(rename = "maxUses")]
+    pub max_uses: u8,
+    #[serde(rename = "boundUserId")]
+    pub bound_user_id: Option<String>,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: String,
+}
+
+impl CouponBuilder {
+    pub fn new(
+        code: String,
+        percent: u8,
+        max_uses: u8,
+        bound_user_id: Option<String>,
+    ) -> Result<Self, CouponBuilderError> {
+        let invalid = |field, reason| Err(Report::new(CouponBuilderError { field, reason }));
+
+        if percent > 90 {
+            return invalid("percent", "must be less than or equal to 90");
+        }
+        if percent == 0 {
+            return invalid("percent", "must be greater than 0");
+        }
+        if max_uses > 100 {
+            return invalid("max_uses", "must be less than or equal to 100");
+        }
+        if max_uses == 0 {
+            return invalid("max_uses", "must be greater than 0");
+        }
+        if code.len() > 64 {
+            return invalid("code", "must be less than or equal to 64 characters");
+        }
+        if code.is_empty() {
+            return invalid("code", "must be greater than 0 characters");
+        }
+
+        let now = Utc::now();
+        let expiry = now + Days::new(7);
+
+        Ok(Self {
+            code,
+            percent,
+            max_uses,
+            bound_user_id,
+            expires_at: expiry.to_rfc3339(),
+        })
+    }
+
+    /// Replaces the default 7 day expiry.
+    pub fn expires_at(mut self, expiry: DateTime<Utc>) -> Result<Self, CouponBuilderError> {
+        let now = Utc::now();
+        if expiry <= now {
+            return Err(Report::new(CouponBuilderError {
+                field: "expires_at",
+                reason: "must be in the future",
+            }));
+        }
+        if expiry > now + Days::new(MAX_COUPON_DAYS) {
+            return Err(Report::new(CouponBuilderError {
+                field: "expires_at",
+                reason: "must be within a year",
+            }));
+        }
+
+        self.expires_at = expiry.to_rfc3339();
+        Ok(self)
+    }
+
+    /// Starts from an existing coupon, for updating it.
+    pub fn from_coupon(coupon: &GMSCouponObject) -> Self {
+        Self {
+            code: coupon.code.clone(),
+            percent: coupon.percent,
+            max_uses: coupon.max_uses,
+            bound_user_id: coupon.bound_user.clone(),
+            expires_at: coupon.expires_at.clone(),
+        }
+    }
+}
+
+/// Furthest in the future a coupon may expire.
+const MAX_COUPON_DAYS: u64 = 365;
+
+impl GmodStoreClient {
+    /// Streams the pages of a list endpoint, following cursors until the last
+    /// page or `MAX_PAGES`, whichever comes first.
+    pub fn paginate<'a, T: DeserializeOwned + 'a>(
+        &'a self,
+        url: String,
+        query: Vec<(&'static str, String)>,
+    ) -> impl Stream<Item = Result<Vec<T>, GMSClientHTTPError>> + 'a {
+        futures::stream::try_unfold(Some((None::<String>, 0)), move |state| {
+            let url = url.clone();
+            let query = query.clone();
+
+            async move {
+                let Some((cursor, page)) = state else {
+                    return Ok(None);
+                };
+
+                if page >= MAX_PAGES {
+                    warn!("Stopped paginating {} after {} pages", url, MAX_PAGES);
+                    return Ok(None);
+                }
+
+                let mut request = self
+                    .client
+                    .get(&url)
+                    .query(&query)
+                    .query(&[("perPage", PER_PAGE)]);
+                if let Some(cursor) = &cursor {
+                    request = request.query(&[("cursor", cursor)]);
+                }
+
+                let response = self
+                    .retry
+                    .execute(Idempotency::Safe, request)
+                    .await
+                    .attach_printable_lazy(|| {
+                        format!("Failed to fetch page {} of {}", page + 1, url)
+                    })
+                    .change_context(GMSClientHTTPError)?
+                    .json::<GMSListResponse<T>>()
+                    .await
+                    .into_report()
+                    .attach_printable("An error occurred whilst deserializing the API response")
+                    .change_context(GMSClientHTTPError)?;
+
+                let next = response
+                    .cursors
+                    .next
+                    .filter(|cursor| !cursor.is_empty())
+                    .map(|cursor| (Some(cursor), page + 1));
+
+                Ok(Some((response.data, next)))
+            }
+        })
+    }
+
+    /// Collects every item of a list endpoint, see [`Self::paginate`].
+    pub async fn list<T: DeserializeOwned>(
+        &self,
+        url: String,
+        query: Vec<(&'static str, String)>,
+    ) -> Result<Vec<T>, GMSClientHTTPError> {
+        self.paginate(url, query).try_concat().await
+    }
+
+    pub async fn create_coupon(
+        &self,
+        addon: &str,
+        coupon: CouponBuilder,
+    ) -> Result<GMSCouponObject, GMSClientHTTPError> {
+        // Format URL
+        let url = format!("{}/products/{}/coupons", self.url, addon);
+
+        // Send Request
+        let response = self
+            .retry
+            .execute(Idempotency::Unsafe, self.client.post(url).json(&coupon))
+            .await
+            .attach_printable("An error occurred while fetching from the API")
+            .change_context(GMSClientHTTPError)?;
+
+        let return_value = response
+            .json::<GMSCouponResponse>()
+            .await
+            .into_report()
+            .attach_printable("An error occurred whilst deserializing the API response")
+            .change_context(GMSClientHTTPError)?;
+
+        Ok(return_value.data)
+    }
+
+    /// Coupon IDs go into the path, so nothing else may reach the API.
+    fn check_coupon_id(coupon_id: &str) -> Result<(), GMSClientHTTPError> {
+        match is_gmodstore_id(coupon_id) {
+            true => Ok(()),
+            false => Err(Report::new(GMSClientHTTPError)
+                .attach_printable(format!("Refusing malformed coupon ID: {}", coupon_id))),
+        }
+    }
+
+    /// Every coupon of a product, optionally only those bound to one GmodStore user.
+    pub async fn get_product_coupons(
+        &self,
+        addon: &str,
+        bound_user_id: Option<&str>,
+    ) -> Result<Vec<GMSCouponObject>, GMSClientHTTPError> {
+        let url = format!("{}/products/{}/coupons", self.url, addon);
+
+        let query = bound_user_id
+            .map(|id| vec![("filter[boundUserId]", id.to_string())])
+            .unwrap_or_default();
+
+        self.list(url, query).await
+    }
+
+    pub async fn get_coupon(
+        &self,
+        addon: &str,
+        coupon_id: &str,
+    ) -> Result<Option<GMSCouponObject>, GMSClientHTTPError> {
+        Self::check_coupon_id(coupon_id)?;
+        let url = format!("{}/products/{}/coupons/{}", self.url, addon, coupon_id);
+
+        let response = match self
+            .retry
+            .execute(Idempotency::Safe, self.client.get(url))
+            .await
+        {
+            Ok(response) => response,
+            Err(report) if report.current_context() == &ApiError::NotFound => return Ok(None),
+            Err(report) => {
+                return Err(report
+                    .attach_printable("An error occurred while fetching from the API")
+                    .change_context(GMSClientHTTPError))
+            }
+        };
+
+        let return_value = response
+            .json::<GMSCouponResponse>()
+            .await
+            .into_report()
+            .attach_printable("An error occurred whilst deserializing the API response")
+            .change_context(GMSClientHTTPError)?;
+
+        Ok(Some(return_value.data))
+    }
+
+    /// Replaces a coupon's details, e.g. to extend its expiry.
+    pub async fn update_coupon(
+        &self,
+        addon: &str,
+        coupon_id: &str,
+        coupon: CouponBuilder,
+    ) -> Result<GMSCouponObject, GMSClientHTTPError> {
+        Self::check_coupon_id(coupon_id)?;
+        let url = format!("{}/products/{}/coupons/{}", self.url, addon, coupon_id);
+
+        let response = self
+            .retry
+            .execute(Idempotency::Unsafe, self.client.put(url).json(&coupon))
+            .await
+            .attach_printable("An error occurred while updating the coupon")
+            .change_context(GMSClientHTTPError)?;
+
+        let return_value = response
+            .json::<GMSCouponResponse>()
+            .await
+            .into_report()
+            .attach_printable("An error occurred whilst deserializing the API response")
+            .change_context(GMSClientHTTPError)?;
+
+        Ok(return_value.data)
+    }
+
+    pub async fn delete_coupon(
+        &self,
+        addon: &str,
+        coupon_id: &str,
+    ) -> Result<(), GMSClientHTTPError> {
+        Self::check_coupon_id(coupon_id)?;
+        let url = format!("{}/products/{}/coupons/{}", self.url, addon, coupon_id);
+
+        self.retry
+            .execute(Idempotency::Delete, self.client.delete(url))
+            .await
+            .attach_printable("Failed to send delete request to GmodStore")
+            .change_context(GMSClientHTTPError)?;
+
+        Ok(())
+    }
+}
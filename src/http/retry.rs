@@ -0,0 +1,263 @@
+use chrono::{DateTime, TimeZone, Utc};
+use error_stack::Report;
+use rand::Rng;
+use reqwest::{header::RETRY_AFTER, RequestBuilder, Response, StatusCode};
+use std::{
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use super::ApiError;
+use crate::config::HttpConfig;
+
+/// How safe it is to send a request more than once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Idempotency {
+    /// Reads, retried on any transient failure.
+    Safe,
+    /// Deletes, only retried when the server certainly didn't act on the request.
+    Delete,
+    /// Writes, never retried.
+    Unsafe,
+}
+
+impl Idempotency {
+    fn retry_error(self, error: &reqwest::Error) -> bool {
+        match self {
+            Self::Safe => error.is_connect() || error.is_timeout() || error.is_request(),
+            Self::Delete => error.is_connect(),
+            Self::Unsafe => false,
+        }
+    }
+
+    fn retry_status(self, status: StatusCode) -> bool {
+        match self {
+            Self::Safe => matches!(
+                status,
+                StatusCode::TOO_MANY_REQUESTS
+                    | StatusCode::BAD_GATEWAY
+                    | StatusCode::SERVICE_UNAVAILABLE
+                    | StatusCode::GATEWAY_TIMEOUT
+            ),
+            // Both mean the request was turned away before being handled.
+            Self::Delete => matches!(
+                status,
+                StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE
+            ),
+            Self::Unsafe => false,
+        }
+    }
+}
+
+/// Retries transient upstream failures with exponential backoff.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Names the upstream in metrics.
+    client: &'static str,
+    max_retries: u32,
+    backoff_base: Duration,
+    backoff_max: Duration,
+    /// Whether a 404 is an ordinary answer from this upstream, e.g. "not linked".
+    not_found_is_success: bool,
+    /// Unix milliseconds of the last successful answer, 0 for never.
+    last_success: Arc<AtomicI64>,
+}
+
+impl RetryPolicy {
+    pub fn new(client: &'static str, config: &HttpConfig) -> Self {
+        Self {
+            client,
+            max_retries: config.max_retries,
+            backoff_base: Duration::from_millis(config.backoff_base_ms),
+            backoff_max: Duration::from_millis(config.backoff_max_ms),
+            not_found_is_success: false,
+            last_success: Arc::new(AtomicI64::new(0)),
+        }
+    }
+
+    /// Counts 404s towards [`RetryPolicy::last_success`], for upstreams that use
+    /// them as an ordinary answer.
+    pub fn not_found_is_success(mut self) -> Self {
+        self.not_found_is_success = true;
+        self
+    }
+
+    /// When the upstream last answered successfully, whatever the request.
+    pub fn last_success(&self) -> Option<DateTime<Utc>> {
+        match self.last_success.load(Ordering::Relaxed) {
+            0 => None,
+            millis => Utc.timestamp_millis_opt(millis).single(),
+        }
+    }
+
+    /// Exponential backoff with full jitter.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let ceiling = self
+            .backoff_base
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.backoff_max);
+        let millis = ceiling.as_millis() as u64;
+        Duration::from_millis(rand::thread_rng().gen_range(0..=millis))
+    }
+
+    /// Sends a request with retries, turning any unsuccessful outcome into an [`ApiError`].
+    pub async fn execute(
+        &self,
+        kind: Idempotency,
+        request: RequestBuilder,
+    ) -> Result<Response, Report<ApiError>> {
+        let (response, failures) = self
+            .send(kind, request)
+            .await
+            .map_err(ApiError::from_transport)?;
+
+        ApiError::check(response).await.map_err(|report| {
+            failures
+                .into_iter()
+                .fold(report, |report, failure| report.attach_printable(failure))
+        })
+    }
+
+    /// Sends a request, retrying transient failures according to the policy.
+    ///
+    /// The last response is returned whatever its status, along with a note for
+    /// every earlier failed attempt, so [`ApiError::check`] can classify it from
+    /// its headers and body. Transport errors carry those notes as attachments.
+    async fn send(
+        &self,
+        kind: Idempotency,
+        request: RequestBuilder,
+    ) -> Result<(Response, Vec<String>), Report<reqwest::Error>> {
+        let mut failures: Vec<String> = Vec::new();
+        let mut pending = Some(request);
+        let mut attempt = 0;
+
+        while let Some(request) = pending.take() {
+            // Requests with streaming bodies can't be cloned, so they get one shot.
+            let current = match request.try_clone() {
+                Some(clone) if attempt < self.max_retries => {
+                    pending = Some(request);
+                    clone
+                }
+                _ => request,
+            };
+
+            let started = Instant::now();
+            let sent = current.send().await;
+            self.observe(&sent, started);
+
+            let delay = match sent {
+                Ok(response) => {
+                    let status = response.status();
+                    if !kind.retry_status(status) {
+                        if attempt > 0 {
+                            debug!("Request returned {} after {} retries", status, attempt);
+                        }
+                        return Ok((response, failures));
+                    }
+
+                    let delay = match retry_after(&response) {
+                        Some(delay) if delay > self.backoff_max => {
+                            failures.push(format!(
+                                "Attempt {} returned {} asking to retry after {:?}, longer than the backoff cap",
+                                attempt + 1,
+                                status,
+                                delay
+                            ));
+                            None
+                        }
+                        Some(delay) => Some(delay),
+                        None => Some(self.backoff(attempt)),
+                    };
+
+                    match delay {
+                        Some(delay) if pending.is_some() => {
+                            failures.push(format!(
+                                "Attempt {} to {} returned {}, retrying in {:?}",
+                                attempt + 1,
+                                response.url(),
+                                status,
+                                delay
+                            ));
+                            delay
+                        }
+                        _ => {
+                            failures.push(format!("Request failed after {} attempts", attempt + 1));
+                            return Ok((response, failures));
+                        }
+                    }
+                }
+                Err(error) => {
+                    if pending.is_none() || !kind.retry_error(&error) {
+                        return Err(exhausted(error, attempt + 1, failures));
+                    }
+
+                    let delay = self.backoff(attempt);
+                    failures.push(format!(
+                        "Attempt {} failed: {}, retrying in {:?}",
+                        attempt + 1,
+                        error,
+                        delay
+                    ));
+                    delay
+                }
+            };
+
+            warn!("{}", failures[failures.len() - 1]);
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+
+        unreachable!("the final attempt always returns")
+    }
+
+    fn observe(&self, sent: &reqwest::Result<Response>, started: Instant) {
+        let status = match sent {
+            Ok(response) => {
+                // Rejected credentials or rate limits mean the upstream isn't usable.
+                let status = response.status();
+                if status.is_success()
+                    || (self.not_found_is_success && status == StatusCode::NOT_FOUND)
+                {
+                    self.last_success
+                        .store(Utc::now().timestamp_millis(), Ordering::Relaxed);
+                }
+                response.status().as_u16().to_string()
+            }
+            Err(_) => "error".to_string(),
+        };
+
+        let metrics = crate::metrics::metrics();
+        metrics
+            .upstream_requests
+            .with_label_values(&[self.client, &status])
+            .inc();
+        metrics
+            .upstream_duration
+            .with_label_values(&[self.client])
+            .observe(started.elapsed().as_secs_f64());
+    }
+}
+
+/// Builds the error for the final attempt, recording every earlier one.
+fn exhausted(
+    error: reqwest::Error,
+    attempts: u32,
+    failures: Vec<String>,
+) -> Report<reqwest::Error> {
+    failures
+        .into_iter()
+        .fold(Report::new(error), |report, failure| {
+            report.attach_printable(failure)
+        })
+        .attach_printable(format!("Request failed after {} attempts", attempts))
+}
+
+/// Parses a `Retry-After` header given in seconds.
+fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response.headers().get(RETRY_AFTER)?.to_str().ok()?;
+    value.trim().parse::<u64>().ok().map(Duration::from_secs)
+}
@@ -1,7 +1,7 @@
 #[macro_use]
 extern crate log;
 
-use error_stack::{Context as ErrorContext, IntoReport, Result, ResultExt};
+use error_stack::{Context as ErrorContext, IntoReport, Report, Result, ResultExt};
 
 use async_trait::async_trait;
 use dotenv::dotenv;
@@ -11,18 +11,34 @@ pub use serenity::model::application::{
     interaction::{Interaction, InteractionResponseType},
 };
 use serenity::{
-    model::{gateway::Ready, prelude::Member},
-    prelude::{Context, EventHandler, GatewayIntents},
+    client::bridge::gateway::{event::ShardStageUpdateEvent, ShardManager},
+    gateway::ConnectionStage,
+    model::{event::ResumedEvent, gateway::Ready, id::GuildId, prelude::Member},
+    prelude::{Context, EventHandler, GatewayIntents, Mutex},
     Client,
 };
+use std::{process::ExitCode, sync::Arc, time::Duration};
 
+use crate::health::GatewayState;
+
+mod audit;
+mod campaigns;
 mod commands;
+mod config;
 mod events;
+mod health;
 mod http;
+mod metrics;
 mod misc;
+mod roles;
+mod server;
+mod shutdown;
+mod steamid;
+mod store;
+mod sync;
+mod template;
 
-use crate::misc::get_env;
-use commands::Command;
+use crate::misc::{get_env, get_guild_id};
 
 #[derive(Debug)]
 struct DiscordBotBuildError;
@@ -46,12 +62,21 @@ impl std::fmt::Display for DiscordBotRuntimeError {
 impl ErrorContext for DiscordBotRuntimeError {}
 
 pub struct Handler {
-    pub http: crate::http::HttpClient,
+    pub http: Arc<crate::http::HttpClient>,
+    pub config: Arc<crate::config::Config>,
+    pub store: Arc<dyn crate::store::Store>,
+    pub health: Arc<crate::health::Health>,
+    pub shutdown: Arc<crate::shutdown::Shutdown>,
+    pub guild_id: Option<GuildId>,
+    pub registration: commands::RegistrationMode,
+    pub commands: commands::CommandRegistry,
+    pub coupon_claims: commands::ClaimLocks,
 }
 
 #[async_trait]
 impl EventHandler for Handler {
     async fn ready(&self, ctx: Context, ready: Ready) {
+        self.health.set_gateway(GatewayState::Connected);
         info!("Connection to Discord established!");
         info!(
             "Connected as: {}#{}",
@@ -59,57 +84,67 @@ impl EventHandler for Handler {
         );
 
         debug!("Attempting to push slash commands...");
-        let commands = InteractionCommand::set_global_application_commands(&ctx.http, |commands| {
-            commands
-                .create_application_command(|command| commands::CouponCommand::register(command))
-                .create_application_command(|command| commands::ForceRolesCommand::register(command))
-                .create_application_command(|command| commands::GmodStoreCommand::register(command))
-                .create_application_command(|command| commands::PurchasesCommand::register(command))
-                .create_application_command(|command| commands::RolesCommand::register(command))
-                .create_application_command(|command| commands::SteamCommand::register(command))
-                .create_application_command(|command| commands::UnlinkCommand::register(command))
-        })
-        .await;
-
-        match commands {
-            Ok(command) => {
-                debug!("Pushed {} slash commands!", command.len());
-
-                for i in command.iter() {
-                    debug!("Registered command: {}", i.name.as_str());
-                }
-            }
-            Err(e) => {
-                error!("Failed to push slash commands");
-                trace!("{:#?}", e);
-            }
+        if let Err(e) =
+            commands::register_commands(&ctx.http, &self.commands, self.registration, self.guild_id)
+                .await
+        {
+            error!("Failed to push slash commands");
+            error!("{:#?}", e);
+        }
+    }
+
+    async fn resume(&self, _ctx: Context, _: ResumedEvent) {
+        info!("Connection to Discord resumed");
+        self.health.set_gateway(GatewayState::Connected);
+    }
+
+    async fn shard_stage_update(&self, _ctx: Context, update: ShardStageUpdateEvent) {
+        if update.new == ConnectionStage::Disconnected {
+            warn!("Connection to Discord lost");
         }
+        self.health.set_gateway(update.new.into());
     }
 
     async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
-        if let Interaction::ApplicationCommand(mut command) = interaction {
+        if let Interaction::ApplicationCommand(command) = interaction {
             debug!(
                 "Received command interaction: {}",
                 command.data.name.as_str()
             );
 
-            if let Err(e) = match command.data.name.as_str() {
-                "coupon" => commands::CouponCommand::execute(self, &mut command, ctx).await,
-                "force-roles" => commands::ForceRolesCommand::execute(self, &mut command, ctx).await,
-                "gmodstore" => commands::GmodStoreCommand::execute(self, &mut command, ctx).await,
-                "purchases" => commands::PurchasesCommand::execute(self, &mut command, ctx).await,
-                "roles" => commands::RolesCommand::execute(self, &mut command, ctx).await,
-                "steam" => commands::SteamCommand::execute(self, &mut command, ctx).await,
-                "unlink" => commands::UnlinkCommand::execute(self, &mut command, ctx).await,
-                _ => {
-                    error!("Unknown command: {}", command.data.name.as_str());
-                    return;
+            // Held until the command has answered, so shutdown waits for it.
+            let Some(_activity) = self.shutdown.track() else {
+                debug!("Refusing command whilst shutting down");
+                if let Err(e) = commands::send_response(
+                    &command,
+                    &ctx,
+                    false,
+                    "The bot is restarting, please try again in a moment.".into(),
+                )
+                .await
+                {
+                    error!("{:#?}", e);
                 }
-            } {
-                debug!("An error occurred whilst running previous command");
-                sentry::capture_error(&e.as_error());
-                error!("{:#?}", e);
-            }
+                return;
+            };
+
+            let Some(registered) = self.commands.get(command.data.name.as_str()) else {
+                warn!("Unknown command: {}", command.data.name.as_str());
+
+                if let Err(e) = commands::send_response(
+                    &command,
+                    &ctx,
+                    false,
+                    "This command is no longer available, please try again in a moment.".into(),
+                )
+                .await
+                {
+                    error!("{:#?}", e);
+                }
+                return;
+            };
+
+            registered.run(self, &command, ctx).await;
         }
     }
 
@@ -121,11 +156,44 @@ impl EventHandler for Handler {
     }
 }
 
-async fn build() -> Result<(), DiscordBotBuildError> {
+async fn build() -> Result<ExitCode, DiscordBotBuildError> {
+    debug!("Loading config");
+    let config = Arc::new(crate::config::Config::load().change_context(DiscordBotBuildError)?);
+    let config_loaded_at = chrono::Utc::now();
+
     debug!("Building HTTP client");
-    let http = crate::http::HttpClient::new().change_context(DiscordBotBuildError)?;
+    let http =
+        Arc::new(crate::http::HttpClient::new(&config).change_context(DiscordBotBuildError)?);
+
+    debug!("Opening database");
+    let store: Arc<dyn crate::store::Store> = Arc::new(
+        crate::store::SqliteStore::open(&config.store.path).change_context(DiscordBotBuildError)?,
+    );
+
+    let guild_id = get_guild_id().change_context(DiscordBotBuildError)?;
 
-    let handler = Handler { http };
+    let registration =
+        commands::RegistrationMode::from_env().change_context(DiscordBotBuildError)?;
+    if registration.requires_guild() && guild_id.is_none() {
+        return Err(Report::new(DiscordBotBuildError).attach_printable(
+            "DISCORD_GUILD must be set when COMMAND_REGISTRATION is guild or both",
+        ));
+    }
+
+    let health = Arc::new(crate::health::Health::new());
+    let shutdown = crate::shutdown::Shutdown::new();
+
+    let handler = Handler {
+        http: http.clone(),
+        config: config.clone(),
+        store: store.clone(),
+        health: health.clone(),
+        shutdown: shutdown.clone(),
+        guild_id,
+        registration,
+        commands: commands::CommandRegistry::default(),
+        coupon_claims: commands::ClaimLocks::default(),
+    };
 
     let discord_token = get_env("DISCORD_TOKEN")
         .attach_printable("Failed to read discord token")
@@ -135,23 +203,136 @@ async fn build() -> Result<(), DiscordBotBuildError> {
     debug!("Building Discord client");
     let mut client = Client::builder(discord_token, intents)
         .event_handler(handler)
+        .raw_event_handler(crate::metrics::GatewayEventCounter)
         .await
         .into_report()
         .attach_printable("Failed to build client")
         .change_context(DiscordBotBuildError)?;
 
-    client
-        .start()
+    if let Some(bind) = config.monitoring.bind {
+        debug!("Starting monitoring server");
+        let state = crate::server::MonitoringState {
+            http: http.clone(),
+            config: config.clone(),
+            health: health.clone(),
+            config_loaded_at,
+        };
+        crate::server::start_monitoring(bind, state, shutdown.clone())
+            .await
+            .change_context(DiscordBotBuildError)?;
+    }
+
+    if config.sync.enabled || config.server.enabled {
+        let Some(guild_id) = guild_id else {
+            return Err(Report::new(DiscordBotBuildError)
+                .attach_printable("Role sync and webhooks require DISCORD_GUILD to be set"));
+        };
+
+        let role_sync = crate::sync::RoleSync {
+            discord: client.cache_and_http.http.clone(),
+            http,
+            config: config.clone(),
+            store,
+            shutdown: shutdown.clone(),
+            guild_id,
+        };
+
+        if config.server.enabled {
+            debug!("Starting webhook server");
+            let state = crate::server::ServerState {
+                sync: role_sync.clone(),
+                linksite_secret: get_env("LINKSITE_WEBHOOK_SECRET")
+                    .ok()
+                    .filter(|secret| !secret.is_empty()),
+                gmodstore_secret: get_env("GMODSTORE_WEBHOOK_SECRET")
+                    .ok()
+                    .filter(|secret| !secret.is_empty()),
+            };
+            crate::server::start(&config.server, state)
+                .await
+                .change_context(DiscordBotBuildError)?;
+        }
+
+        if config.sync.enabled {
+            debug!("Starting role sync task");
+            role_sync.spawn();
+        }
+    }
+
+    let mut draining = tokio::spawn(drain_on_signal(
+        shutdown.clone(),
+        client.shard_manager.clone(),
+        Duration::from_secs(config.shutdown.grace_period),
+    ));
+
+    // Draining ends by shutting the shards down, which also ends `start`. Whichever
+    // finishes first wins, as `start` never returns if it is still connecting.
+    let drained = tokio::select! {
+        result = client.start() => {
+            result
+                .into_report()
+                .attach_printable("Failed to start client")
+                .change_context(DiscordBotBuildError)?;
+
+            if !shutdown.is_closing() {
+                return Err(Report::new(DiscordBotBuildError)
+                    .attach_printable("Discord client stopped without being asked to"));
+            }
+            (&mut draining).await.unwrap_or(false)
+        }
+        drained = &mut draining => drained.unwrap_or(false),
+    };
+
+    Ok(if drained {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(EXIT_DRAIN_TIMEOUT)
+    })
+}
+
+/// Exit status when work was still running at the end of the grace period.
+const EXIT_DRAIN_TIMEOUT: u8 = 2;
+
+/// Waits for a shutdown signal, then stops taking new work, gives running work
+/// until the grace period to finish and disconnects from Discord.
+///
+/// Returns whether everything finished in time.
+async fn drain_on_signal(
+    shutdown: Arc<crate::shutdown::Shutdown>,
+    shard_manager: Arc<Mutex<ShardManager>>,
+    grace_period: Duration,
+) -> bool {
+    let signal = crate::shutdown::signal().await;
+    info!(
+        "Received {}, shutting down with {} tasks in flight",
+        signal,
+        shutdown.active()
+    );
+    shutdown.close();
+
+    let drained = tokio::time::timeout(grace_period, shutdown.wait_idle())
         .await
-        .into_report()
-        .attach_printable("Failed to start client")
-        .change_context(DiscordBotBuildError)?;
+        .is_ok();
+    if drained {
+        info!("All work finished, disconnecting from Discord");
+    } else {
+        warn!(
+            "Abandoning {} tasks still running after {}s",
+            shutdown.active(),
+            grace_period.as_secs()
+        );
+    }
 
-    Ok(())
+    if let Some(client) = sentry::Hub::current().client() {
+        client.flush(Some(Duration::from_secs(2)));
+    }
+
+    shard_manager.lock().await.shutdown_all().await;
+    drained
 }
 
 #[tokio::main]
-async fn main() -> Result<(), DiscordBotRuntimeError> {
+async fn main() -> Result<ExitCode, DiscordBotRuntimeError> {
     #[cfg(not(debug_assertions))]
     let _guard = sentry::init((
         env!("SENTRY_DSN"),
@@ -177,7 +358,5 @@ async fn main() -> Result<(), DiscordBotRuntimeError> {
         .attach_printable("Failed to initialize logger")
         .change_context(DiscordBotRuntimeError)?;
 
-    build().await.change_context(DiscordBotRuntimeError)?;
-
-    Ok(())
+    build().await.change_context(DiscordBotRuntimeError)
 }
@@ -0,0 +1,159 @@
+use async_trait::async_trait;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+use serenity::{client::RawEventHandler, model::event::Event, prelude::Context};
+use std::sync::LazyLock;
+
+use crate::http::LinkCache;
+
+/// Seconds, from a fast cache-adjacent call up to a request that exhausted its retries.
+const LATENCY_BUCKETS: &[f64] = &[0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+/// Process wide metrics, recorded from wherever the event happens.
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+pub struct Metrics {
+    registry: Registry,
+    /// By `command` and `outcome`, see [`crate::audit::Outcome::label`].
+    pub commands: IntCounterVec,
+    pub command_duration: HistogramVec,
+    /// Every attempt, by `client` and response `status` (`error` when none came back).
+    pub upstream_requests: IntCounterVec,
+    pub upstream_duration: HistogramVec,
+    /// By `change`, either `added` or `removed`.
+    pub role_changes: IntCounterVec,
+    pub coupons_issued: IntCounterVec,
+    pub gateway_events: IntCounterVec,
+    /// Link API cache lookups, by `cache` and `result`, either `hit` or `miss`.
+    pub cache_requests: IntCounterVec,
+    cache_entries: IntGaugeVec,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry =
+            Registry::new_custom(Some("leybot".to_string()), None).expect("metric prefix is valid");
+
+        let counter = |name: &str, help: &str, labels: &[&str]| {
+            let counter = IntCounterVec::new(Opts::new(name, help), labels)
+                .expect("metric definition is valid");
+            registry
+                .register(Box::new(counter.clone()))
+                .expect("metric is registered once");
+            counter
+        };
+        let histogram = |name: &str, help: &str, labels: &[&str]| {
+            let histogram = HistogramVec::new(
+                HistogramOpts::new(name, help).buckets(LATENCY_BUCKETS.to_vec()),
+                labels,
+            )
+            .expect("metric definition is valid");
+            registry
+                .register(Box::new(histogram.clone()))
+                .expect("metric is registered once");
+            histogram
+        };
+
+        let commands = counter(
+            "commands_total",
+            "Slash commands run, by outcome",
+            &["command", "outcome"],
+        );
+        let command_duration = histogram(
+            "command_duration_seconds",
+            "Time taken to run and answer a slash command",
+            &["command"],
+        );
+        let upstream_requests = counter(
+            "upstream_requests_total",
+            "Requests to the link API and GmodStore, counting every retry",
+            &["client", "status"],
+        );
+        let upstream_duration = histogram(
+            "upstream_request_duration_seconds",
+            "Latency of single requests to the link API and GmodStore",
+            &["client"],
+        );
+        let role_changes = counter(
+            "role_changes_total",
+            "Managed roles added to or removed from members",
+            &["change"],
+        );
+        let coupons_issued = counter(
+            "coupons_issued_total",
+            "Coupons created on GmodStore, by campaign",
+            &["campaign"],
+        );
+        let gateway_events = counter(
+            "gateway_events_total",
+            "Events received from the Discord gateway",
+            &["event"],
+        );
+
+        let cache_requests = counter(
+            "cache_requests_total",
+            "Link API lookups, by whether the cache answered them",
+            &["cache", "result"],
+        );
+
+        let cache_entries = IntGaugeVec::new(
+            Opts::new("cache_entries", "Entries held by the link API cache"),
+            &["cache"],
+        )
+        .expect("metric definition is valid");
+        registry
+            .register(Box::new(cache_entries.clone()))
+            .expect("metric is registered once");
+
+        Self {
+            registry,
+            commands,
+            command_duration,
+            upstream_requests,
+            upstream_duration,
+            role_changes,
+            coupons_issued,
+            gateway_events,
+            cache_requests,
+            cache_entries,
+        }
+    }
+
+    /// Renders every metric in the Prometheus text format, reading the cache
+    /// sizes at the time of the scrape.
+    pub fn render(&self, cache: &LinkCache) -> String {
+        for (name, stats) in [
+            ("users", cache.user_stats()),
+            ("purchases", cache.purchase_stats()),
+        ] {
+            self.cache_entries
+                .with_label_values(&[name])
+                .set(stats.entries as i64);
+        }
+
+        let mut buffer = Vec::new();
+        if let Err(e) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            error!("Failed to encode metrics: {}", e);
+        }
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
+
+/// Counts every gateway event by name, alongside the regular event handler.
+pub struct GatewayEventCounter;
+
+#[async_trait]
+impl RawEventHandler for GatewayEventCounter {
+    async fn raw_event(&self, _ctx: Context, event: Event) {
+        let event_type = event.event_type();
+        metrics()
+            .gateway_events
+            .with_label_values(&[event_type.name().unwrap_or("UNKNOWN")])
+            .inc();
+    }
+}
@@ -1,4 +1,5 @@
 use error_stack::{Context, IntoReport, Result, ResultExt};
+use serenity::model::id::GuildId;
 use std::env;
 
 #[derive(Debug)]
@@ -20,6 +21,21 @@ pub fn get_env(key: &str) -> Result<String, EnvironmentError> {
     Ok(env_var)
 }
 
+/// Reads `DISCORD_GUILD`, treating a missing or empty value as unset.
+pub fn get_guild_id() -> Result<Option<GuildId>, EnvironmentError> {
+    let guild_id = match env::var("DISCORD_GUILD") {
+        Ok(guild_id) if !guild_id.is_empty() => guild_id,
+        _ => return Ok(None),
+    };
+
+    let guild_id = guild_id
+        .parse::<u64>()
+        .into_report()
+        .attach_printable_lazy(|| format!("DISCORD_GUILD is not a valid guild ID: {}", guild_id))
+        .change_context(EnvironmentError)?;
+    Ok(Some(GuildId(guild_id)))
+}
+
 pub fn bool_to_emoji(bool: bool) -> &'static str {
     if bool {
         "✅"
@@ -0,0 +1,206 @@
+use error_stack::{Context, IntoReport, Result, ResultExt};
+use serenity::{
+    http::Http,
+    model::{guild::Member, id::RoleId, mention::Mention},
+};
+
+use crate::{
+    config::{Config, RolesConfig},
+    http::ApiPurchaseObject,
+};
+
+#[derive(Debug)]
+pub struct RoleAssignmentError;
+
+impl std::fmt::Display for RoleAssignmentError {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt.write_str("Bot Error: An error occurred whilst assigning roles")
+    }
+}
+
+impl Context for RoleAssignmentError {}
+
+/// Outcome of reconciling a member's managed roles.
+///
+/// `unchanged` only lists managed roles the member kept, roles the bot does
+/// not manage are never touched or reported.
+#[derive(Debug, Default)]
+pub struct RoleDiff {
+    pub added: Vec<RoleId>,
+    pub removed: Vec<RoleId>,
+    pub unchanged: Vec<RoleId>,
+}
+
+impl RoleDiff {
+    pub fn has_changes(&self) -> bool {
+        !self.added.is_empty() || !self.removed.is_empty()
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "**Added:** {}\n**Removed:** {}\n**Unchanged:** {}",
+            mention_roles(&self.added),
+            mention_roles(&self.removed),
+            mention_roles(&self.unchanged)
+        )
+    }
+}
+
+fn mention_roles(roles: &[RoleId]) -> String {
+    if roles.is_empty() {
+        return "None".to_string();
+    }
+
+    roles
+        .iter()
+        .map(|role| Mention::Role(*role).to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Every role the bot is responsible for, verified and product roles alike.
+pub fn managed_roles(config: &Config) -> Vec<RoleId> {
+    let mut roles = vec![config.roles.verified];
+    roles.extend(config.products.iter().filter_map(|product| product.role));
+    roles
+}
+
+/// Roles the member should hold, `None` purchases meaning the member is not linked.
+pub fn desired_roles(config: &Config, purchases: Option<&ApiPurchaseObject>) -> Vec<RoleId> {
+    let Some(purchases) = purchases else {
+        return Vec::new();
+    };
+
+    let mut roles = vec![config.roles.verified];
+    roles.extend(
+        config
+            .products
+            .iter()
+            .filter(|product| purchases.owns(&product.key))
+            .filter_map(|product| product.role),
+    );
+    roles
+}
+
+/// Works out which managed roles to add and remove without touching Discord.
+pub fn plan(
+    config: &Config,
+    current: &[RoleId],
+    purchases: Option<&ApiPurchaseObject>,
+) -> RoleDiff {
+    let desired = desired_roles(config, purchases);
+    let mut diff = RoleDiff::default();
+
+    for role in managed_roles(config) {
+        let has = current.contains(&role);
+        let wants = desired.contains(&role);
+
+        match (has, wants) {
+            (false, true) => diff.added.push(role),
+            (true, false) => diff.removed.push(role),
+            (true, true) => diff.unchanged.push(role),
+            (false, false) => {}
+        }
+    }
+
+    diff
+}
+
+/// Brings a member's managed roles in line with their purchases.
+///
+/// Pass `None` for members that are not (or no longer) linked, this strips
+/// every managed role from them.
+pub async fn reconcile(
+    http: &Http,
+    member: &mut Member,
+    config: &Config,
+    purchases: Option<&ApiPurchaseObject>,
+) -> Result<RoleDiff, RoleAssignmentError> {
+    let diff = plan(config, &member.roles, purchases);
+
+    if !diff.has_changes() {
+        return Ok(diff);
+    }
+
+    // Only the diff is applied, one role at a time, so roles changed by someone else
+    // since the member was fetched are left alone.
+    let metrics = crate::metrics::metrics();
+    let (guild_id, user_id) = (member.guild_id.0, member.user.id.0);
+
+    for role in &diff.added {
+        http.add_member_role(guild_id, user_id, role.0, None)
+            .await
+            .into_report()
+            .attach_printable_lazy(|| {
+                format!("Failed to add role {} to {}", role, member.user.tag())
+            })
+            .change_context(RoleAssignmentError)?;
+
+        member.roles.push(*role);
+        metrics.role_changes.with_label_values(&["added"]).inc();
+    }
+
+    for role in &diff.removed {
+        http.remove_member_role(guild_id, user_id, role.0, None)
+            .await
+            .into_report()
+            .attach_printable_lazy(|| {
+                format!("Failed to remove role {} from {}", role, member.user.tag())
+            })
+            .change_context(RoleAssignmentError)?;
+
+        member.roles.retain(|current| current != role);
+        metrics.role_changes.with_label_values(&["removed"]).inc();
+    }
+
+    debug!(
+        "Reconciled roles for {}: +{} -{}",
+        member.user.tag(),
+        diff.added.len(),
+        diff.removed.len()
+    );
+
+    Ok(diff)
+}
+
+/// Adds the given roles to a member, skipping any they already have.
+///
+/// Returns the roles that were actually added.
+pub async fn grant_roles(
+    http: &Http,
+    member: &mut Member,
+    roles: &[RoleId],
+) -> Result<Vec<RoleId>, RoleAssignmentError> {
+    let missing: Vec<RoleId> = roles
+        .iter()
+        .filter(|role| !member.roles.contains(role))
+        .copied()
+        .collect();
+
+    // One role at a time, so roles given by someone else at the same moment stay.
+    let metrics = crate::metrics::metrics();
+    let (guild_id, user_id) = (member.guild_id.0, member.user.id.0);
+
+    for role in &missing {
+        http.add_member_role(guild_id, user_id, role.0, None)
+            .await
+            .into_report()
+            .attach_printable_lazy(|| {
+                format!("Failed to add role {} to {}", role, member.user.tag())
+            })
+            .change_context(RoleAssignmentError)?;
+
+        member.roles.push(*role);
+        metrics.role_changes.with_label_values(&["added"]).inc();
+    }
+
+    Ok(missing)
+}
+
+pub async fn grant_verified_role(
+    http: &Http,
+    member: &mut Member,
+    config: &RolesConfig,
+) -> Result<Vec<RoleId>, RoleAssignmentError> {
+    grant_roles(http, member, &[config.verified]).await
+}
@@ -0,0 +1,196 @@
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+};
+use serde::Deserialize;
+use serenity::model::{id::UserId, mention::Mention};
+use std::sync::Arc;
+
+use super::ServerState;
+
+const SIGNATURE_HEADER: &str = "x-gmodstore-signature";
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+enum GmodStoreEventKind {
+    #[serde(rename = "purchase.created")]
+    Purchased,
+    #[serde(rename = "purchase.refunded")]
+    Refunded,
+    /// Anything GmodStore adds later, acknowledged and ignored.
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Deserialize, Debug)]
+struct GmodStorePurchase {
+    #[serde(rename = "userId")]
+    user_id: String,
+    #[serde(rename = "productId")]
+    product_id: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct GmodStoreEvent {
+    /// Stays the same when a delivery is retried.
+    id: String,
+    event: GmodStoreEventKind,
+    /// Only read once the event is known to be about a purchase.
+    #[serde(default)]
+    data: serde_json::Value,
+}
+
+/// Updates the buyer's roles when GmodStore reports a purchase or refund, and
+/// announces new purchases if configured to.
+pub async fn receive(
+    State(state): State<Arc<ServerState>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> StatusCode {
+    let Some(secret) = &state.gmodstore_secret else {
+        return StatusCode::NOT_FOUND;
+    };
+
+    if !super::signature::verify(secret, &headers, SIGNATURE_HEADER, &body) {
+        warn!("Rejected GmodStore webhook with an invalid signature");
+        return StatusCode::UNAUTHORIZED;
+    }
+
+    // Shutting down, the sender will retry.
+    let Some(_activity) = state.sync.shutdown.track() else {
+        return StatusCode::SERVICE_UNAVAILABLE;
+    };
+
+    let event = match serde_json::from_slice::<GmodStoreEvent>(&body) {
+        Ok(event) => event,
+        Err(e) => {
+            warn!("Rejected malformed GmodStore webhook: {}", e);
+            return StatusCode::BAD_REQUEST;
+        }
+    };
+
+    if event.event == GmodStoreEventKind::Unknown {
+        debug!("Ignoring unknown GmodStore event {}", event.id);
+        return StatusCode::NO_CONTENT;
+    }
+
+    let purchase = match serde_json::from_value::<GmodStorePurchase>(event.data) {
+        Ok(purchase) => purchase,
+        Err(e) => {
+            warn!("Rejected malformed GmodStore webhook {}: {}", event.id, e);
+            return StatusCode::BAD_REQUEST;
+        }
+    };
+
+    debug!(
+        "Received GmodStore event {} {:?} for {} on {}",
+        event.id, event.event, purchase.user_id, purchase.product_id
+    );
+
+    let linked = match state
+        .sync
+        .http
+        .link_client
+        .get_user_by_gmodstore(&purchase.user_id)
+        .await
+    {
+        Ok(user) => user.and_then(|user| user.discord_id),
+        // Let GmodStore retry later.
+        Err(e) => {
+            error!("{:#?}", e);
+            return StatusCode::BAD_GATEWAY;
+        }
+    };
+
+    let mut buyer = None;
+    if let Some(discord_id) = linked {
+        // Purchases are cached, and this event is exactly what makes them stale.
+        state
+            .sync
+            .http
+            .link_client
+            .cache
+            .invalidate_discord(discord_id);
+
+        match state.sync.sync_user(UserId(discord_id)).await {
+            Ok(Some((_, diff))) => {
+                if diff.has_changes() {
+                    info!(
+                        "Updated roles for {} after GmodStore event {:?}: +{} -{}",
+                        discord_id,
+                        event.event,
+                        diff.added.len(),
+                        diff.removed.len()
+                    );
+                }
+                buyer = Some(UserId(discord_id));
+            }
+            Ok(None) => debug!("{} is not in the guild, nothing to update", discord_id),
+            Err(e) => {
+                error!("{:#?}", e);
+                return StatusCode::BAD_GATEWAY;
+            }
+        }
+    } else {
+        debug!(
+            "GmodStore user {} is not linked to Discord",
+            purchase.user_id
+        );
+    }
+
+    // Roles are safe to sync again, but a retried delivery mustn't be announced twice.
+    if event.event == GmodStoreEventKind::Purchased {
+        match state.sync.store.record_delivery("gmodstore", &event.id) {
+            Ok(true) => announce(&state, &purchase, buyer).await,
+            Ok(false) => debug!("Already announced GmodStore event {}", event.id),
+            Err(e) => {
+                error!("{:#?}", e);
+                announce(&state, &purchase, buyer).await;
+            }
+        }
+    }
+
+    StatusCode::NO_CONTENT
+}
+
+/// Posts a purchase to the announcement channel, buyers outside the guild stay anonymous.
+async fn announce(state: &ServerState, purchase: &GmodStorePurchase, buyer: Option<UserId>) {
+    let config = &state.sync.config;
+    let Some(channel) = config.announcements.channel else {
+        return;
+    };
+
+    let product = config
+        .products
+        .iter()
+        .find(|product| product.gmodstore_id.as_deref() == Some(&purchase.product_id));
+    let Some(product) = product else {
+        debug!(
+            "Not announcing purchase of {}, it isn't in the product catalog",
+            purchase.product_id
+        );
+        return;
+    };
+
+    let buyer = match buyer {
+        Some(buyer) if !config.announcements.anonymous => Mention::User(buyer).to_string(),
+        _ => "Someone".to_string(),
+    };
+
+    let result = channel
+        .send_message(&*state.sync.discord, |message| {
+            message
+                .embed(|embed| {
+                    embed
+                        .title("New Purchase")
+                        .description(format!("{} just bought **{}**!", buyer, product.label()))
+                        .colour(serenity::utils::Colour::from(0xBF8AE0))
+                })
+                .allowed_mentions(|mentions| mentions.empty_parse())
+        })
+        .await;
+
+    if let Err(e) = result {
+        error!("Failed to announce purchase: {:#?}", e);
+    }
+}
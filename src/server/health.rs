@@ -0,0 +1,80 @@
+use axum::{extract::State, http::StatusCode, Json};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::Arc;
+
+use super::MonitoringState;
+use crate::health::GatewayState;
+
+#[derive(Serialize)]
+struct GatewayReport {
+    state: GatewayState,
+    /// Seconds spent in the current state.
+    since: u64,
+}
+
+/// The config the bot is running with, it won't start with an invalid one.
+#[derive(Serialize)]
+struct ConfigReport {
+    valid: bool,
+    loaded_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+pub struct HealthReport {
+    healthy: bool,
+    gateway: GatewayReport,
+    link_api_last_success: Option<DateTime<Utc>>,
+    gmodstore_last_success: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    config: Option<ConfigReport>,
+}
+
+impl HealthReport {
+    fn new(state: &MonitoringState, healthy: bool, config: Option<ConfigReport>) -> Self {
+        let (gateway, since) = state.health.gateway();
+        let http = &state.http;
+
+        Self {
+            healthy,
+            gateway: GatewayReport {
+                state: gateway,
+                since: since.as_secs(),
+            },
+            link_api_last_success: http.link_client.last_success(),
+            gmodstore_last_success: http.gmod_store_client.last_success(),
+            config,
+        }
+    }
+}
+
+fn respond(report: HealthReport) -> (StatusCode, Json<HealthReport>) {
+    let status = match report.healthy {
+        true => StatusCode::OK,
+        false => StatusCode::SERVICE_UNAVAILABLE,
+    };
+    (status, Json(report))
+}
+
+/// Fails once the gateway has been away longer than the configured grace period,
+/// which is the signal to restart the bot.
+pub async fn live(State(state): State<Arc<MonitoringState>>) -> (StatusCode, Json<HealthReport>) {
+    let (gateway, since) = state.health.gateway();
+    let healthy = gateway == GatewayState::Connected
+        || since.as_secs() < state.config.monitoring.unhealthy_after;
+
+    respond(HealthReport::new(&state, healthy, None))
+}
+
+/// Ready while connected to the gateway.
+pub async fn ready(State(state): State<Arc<MonitoringState>>) -> (StatusCode, Json<HealthReport>) {
+    let config = ConfigReport {
+        valid: true,
+        loaded_at: state.config_loaded_at,
+    };
+
+    let (gateway, _) = state.health.gateway();
+    let healthy = gateway == GatewayState::Connected;
+
+    respond(HealthReport::new(&state, healthy, Some(config)))
+}
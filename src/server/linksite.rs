@@ -0,0 +1,112 @@
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+};
+use serde::Deserialize;
+use serenity::model::id::UserId;
+use std::sync::Arc;
+
+use super::ServerState;
+
+const SIGNATURE_HEADER: &str = "x-linksite-signature";
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum LinkSiteEventKind {
+    Linked,
+    Unlinked,
+    PurchasesChanged,
+    /// Anything the link site adds later, acknowledged and ignored.
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Deserialize, Debug)]
+struct LinkSiteEvent {
+    event: LinkSiteEventKind,
+    /// Only required for the events above, new ones may not be about a member.
+    #[serde(rename = "discordId")]
+    discord_id: Option<u64>,
+}
+
+/// Reconciles a member's roles as soon as the linking site tells us something changed.
+///
+/// Every event is handled the same way, the cached link state is dropped and the
+/// member re-synced, so a replayed or out of order event can't leave stale roles.
+pub async fn receive(
+    State(state): State<Arc<ServerState>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> StatusCode {
+    let Some(secret) = &state.linksite_secret else {
+        return StatusCode::NOT_FOUND;
+    };
+
+    if !super::signature::verify(secret, &headers, SIGNATURE_HEADER, &body) {
+        warn!("Rejected link site webhook with an invalid signature");
+        return StatusCode::UNAUTHORIZED;
+    }
+
+    // Shutting down, the sender will retry.
+    let Some(_activity) = state.sync.shutdown.track() else {
+        return StatusCode::SERVICE_UNAVAILABLE;
+    };
+
+    let event = match serde_json::from_slice::<LinkSiteEvent>(&body) {
+        Ok(event) => event,
+        Err(e) => {
+            warn!("Rejected malformed link site webhook: {}", e);
+            return StatusCode::BAD_REQUEST;
+        }
+    };
+
+    if event.event == LinkSiteEventKind::Unknown {
+        debug!("Ignoring unknown link site event");
+        return StatusCode::NO_CONTENT;
+    }
+
+    let Some(discord_id) = event.discord_id else {
+        warn!(
+            "Rejected malformed link site webhook: {:?} has no discordId",
+            event.event
+        );
+        return StatusCode::BAD_REQUEST;
+    };
+
+    debug!(
+        "Received link site event {:?} for {}",
+        event.event, discord_id
+    );
+
+    state
+        .sync
+        .http
+        .link_client
+        .cache
+        .invalidate_discord(discord_id);
+
+    match state.sync.sync_user(UserId(discord_id)).await {
+        Ok(Some((_, diff))) => {
+            if diff.has_changes() {
+                info!(
+                    "Updated roles for {} after link site event {:?}: +{} -{}",
+                    discord_id,
+                    event.event,
+                    diff.added.len(),
+                    diff.removed.len()
+                );
+            }
+            StatusCode::NO_CONTENT
+        }
+        Ok(None) => {
+            debug!("{} is not in the guild, nothing to update", discord_id);
+            StatusCode::NO_CONTENT
+        }
+        // Let the link site retry later.
+        Err(e) => {
+            error!("{:#?}", e);
+            StatusCode::BAD_GATEWAY
+        }
+    }
+}
@@ -0,0 +1,11 @@
+use axum::{extract::State, http::header, response::IntoResponse};
+use std::sync::Arc;
+
+use super::MonitoringState;
+
+/// Prometheus scrape endpoint.
+pub async fn render(State(state): State<Arc<MonitoringState>>) -> impl IntoResponse {
+    let body = crate::metrics::metrics().render(&state.http.link_client.cache);
+
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body)
+}
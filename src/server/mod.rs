@@ -0,0 +1,117 @@
+use axum::{
+    routing::{get, post},
+    Router,
+};
+use chrono::{DateTime, Utc};
+use error_stack::{Context, IntoReport, Report, Result, ResultExt};
+use std::{net::SocketAddr, sync::Arc};
+use tokio::{net::TcpListener, task::JoinHandle};
+
+use crate::{
+    config::{Config, ServerConfig},
+    health::Health,
+    http::HttpClient,
+    shutdown::Shutdown,
+    sync::RoleSync,
+};
+
+mod gmodstore;
+mod health;
+mod linksite;
+mod metrics;
+mod signature;
+
+#[derive(Debug)]
+pub struct ServerError;
+
+impl std::fmt::Display for ServerError {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt.write_str("Server Error: An error occurred whilst running the webhook server")
+    }
+}
+
+impl Context for ServerError {}
+
+/// Shared by every request handler.
+pub struct ServerState {
+    pub sync: RoleSync,
+    /// Webhooks from the linking site are refused while this is unset.
+    pub linksite_secret: Option<String>,
+    /// Webhooks from GmodStore are refused while this is unset.
+    pub gmodstore_secret: Option<String>,
+}
+
+/// Shared by the monitoring endpoints, which run without a guild or webhooks.
+pub struct MonitoringState {
+    pub http: Arc<HttpClient>,
+    pub config: Arc<Config>,
+    pub health: Arc<Health>,
+    /// When the running config was loaded, it is never reloaded.
+    pub config_loaded_at: DateTime<Utc>,
+}
+
+/// Binds the webhook server and serves it in the background, until shutdown begins.
+pub async fn start(
+    config: &ServerConfig,
+    state: ServerState,
+) -> Result<JoinHandle<()>, ServerError> {
+    if state.linksite_secret.is_none() {
+        warn!("LINKSITE_WEBHOOK_SECRET is not set, link site webhooks will be refused");
+    }
+    if state.gmodstore_secret.is_none() {
+        warn!("GMODSTORE_WEBHOOK_SECRET is not set, GmodStore webhooks will be refused");
+    }
+
+    let shutdown = state.sync.shutdown.clone();
+    let router = Router::new()
+        .route("/webhooks/linksite", post(linksite::receive))
+        .route("/webhooks/gmodstore", post(gmodstore::receive))
+        .with_state(Arc::new(state));
+
+    serve(config.bind, router, shutdown, "Webhook server").await
+}
+
+/// Binds the monitoring server and serves it in the background, until shutdown begins.
+pub async fn start_monitoring(
+    bind: SocketAddr,
+    state: MonitoringState,
+    shutdown: Arc<Shutdown>,
+) -> Result<JoinHandle<()>, ServerError> {
+    let router = Router::new()
+        .route("/metrics", get(metrics::render))
+        .route("/health/live", get(health::live))
+        .route("/health/ready", get(health::ready))
+        .with_state(Arc::new(state));
+
+    serve(bind, router, shutdown, "Monitoring server").await
+}
+
+/// Binding happens up front so a taken port fails the build instead of a task.
+async fn serve(
+    bind: SocketAddr,
+    router: Router,
+    shutdown: Arc<Shutdown>,
+    name: &'static str,
+) -> Result<JoinHandle<()>, ServerError> {
+    let listener = TcpListener::bind(bind)
+        .await
+        .into_report()
+        .attach_printable_lazy(|| format!("Failed to bind {} to {}", name, bind))
+        .change_context(ServerError)?;
+
+    info!("{} listening on {}", name, bind);
+
+    Ok(tokio::spawn(async move {
+        let server = axum::serve(listener, router)
+            .with_graceful_shutdown(async move { shutdown.closed().await });
+
+        if let Err(e) = server.await {
+            error!(
+                "{:#?}",
+                Report::new(e)
+                    .attach_printable(format!("{} stopped", name))
+                    .change_context(ServerError)
+            );
+        }
+    }))
+}
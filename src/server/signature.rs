@@ -0,0 +1,81 @@
+use axum::http::HeaderMap;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+/// Checks a `sha256=<hex>` header against the HMAC-SHA256 of the raw request body.
+///
+/// The comparison is constant time, a missing or malformed header never verifies.
+pub fn verify(secret: &str, headers: &HeaderMap, header: &str, body: &[u8]) -> bool {
+    let Some(signature) = headers.get(header).and_then(|value| value.to_str().ok()) else {
+        return false;
+    };
+    let signature = signature.strip_prefix("sha256=").unwrap_or(signature);
+
+    let Ok(signature) = hex::decode(signature.trim()) else {
+        return false;
+    };
+
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret.as_bytes()) else {
+        return false;
+    };
+    mac.update(body);
+    mac.verify_slice(&signature).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "x-test-signature";
+    const BODY: &[u8] = b"The quick brown fox jumps over the lazy dog";
+    /// HMAC-SHA256 of `BODY` with the key `key`.
+    const SIGNATURE: &str = "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8";
+
+    fn headers(signature: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(HEADER, signature.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn accepts_valid_signatures() {
+        let prefixed = format!("sha256={}", SIGNATURE);
+        assert!(verify("key", &headers(&prefixed), HEADER, BODY));
+        assert!(verify("key", &headers(SIGNATURE), HEADER, BODY));
+        assert!(verify(
+            "key",
+            &headers(&SIGNATURE.to_uppercase()),
+            HEADER,
+            BODY
+        ));
+    }
+
+    #[test]
+    fn rejects_wrong_secret() {
+        assert!(!verify("other", &headers(SIGNATURE), HEADER, BODY));
+    }
+
+    #[test]
+    fn rejects_tampered_body() {
+        assert!(!verify(
+            "key",
+            &headers(SIGNATURE),
+            HEADER,
+            b"The quick brown fox jumps over the lazy cat"
+        ));
+    }
+
+    #[test]
+    fn rejects_missing_header() {
+        assert!(!verify("key", &HeaderMap::new(), HEADER, BODY));
+        assert!(!verify("key", &headers(SIGNATURE), "x-other", BODY));
+    }
+
+    #[test]
+    fn rejects_bad_hex() {
+        assert!(!verify("key", &headers("sha256=not hex"), HEADER, BODY));
+        assert!(!verify("key", &headers(""), HEADER, BODY));
+        // Valid hex, but only half the signature.
+        assert!(!verify("key", &headers(&SIGNATURE[..32]), HEADER, BODY));
+    }
+}
@@ -0,0 +1,107 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+use tokio::sync::{watch, Notify};
+
+/// Coordinates a graceful shutdown with everything still doing work.
+///
+/// Work registers itself with [`Shutdown::track`], which refuses once shutdown
+/// has begun, and the guard it returns keeps the bot alive until dropped.
+pub struct Shutdown {
+    closing: watch::Sender<bool>,
+    active: AtomicUsize,
+    idle: Notify,
+}
+
+/// Marks a piece of work as in flight until dropped.
+pub struct ActivityGuard {
+    shutdown: Arc<Shutdown>,
+}
+
+impl Drop for ActivityGuard {
+    fn drop(&mut self) {
+        if self.shutdown.active.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.shutdown.idle.notify_waiters();
+        }
+    }
+}
+
+impl Shutdown {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            closing: watch::Sender::new(false),
+            active: AtomicUsize::new(0),
+            idle: Notify::new(),
+        })
+    }
+
+    /// Registers new work, `None` once shutdown has begun and it should be turned away.
+    pub fn track(self: &Arc<Self>) -> Option<ActivityGuard> {
+        // Counted before checking, so `wait_idle` can't miss work that slips in.
+        self.active.fetch_add(1, Ordering::AcqRel);
+        let guard = ActivityGuard {
+            shutdown: self.clone(),
+        };
+
+        match self.is_closing() {
+            true => None,
+            false => Some(guard),
+        }
+    }
+
+    pub fn is_closing(&self) -> bool {
+        *self.closing.borrow()
+    }
+
+    /// Stops accepting new work.
+    pub fn close(&self) {
+        self.closing.send_replace(true);
+    }
+
+    /// Resolves once [`Shutdown::close`] has been called.
+    pub async fn closed(&self) {
+        let mut closing = self.closing.subscribe();
+        // The sender lives as long as `self`, so this can't fail.
+        let _ = closing.wait_for(|closing| *closing).await;
+    }
+
+    /// Resolves once no tracked work is left.
+    pub async fn wait_idle(&self) {
+        loop {
+            let idle = self.idle.notified();
+            if self.active() == 0 {
+                return;
+            }
+            idle.await;
+        }
+    }
+
+    pub fn active(&self) -> usize {
+        self.active.load(Ordering::Acquire)
+    }
+}
+
+/// Resolves on SIGINT, or SIGTERM on Unix, with the name of the signal.
+pub async fn signal() -> &'static str {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => return "SIGINT",
+                    _ = terminate.recv() => return "SIGTERM",
+                }
+            }
+            Err(e) => error!("Failed to listen for SIGTERM: {}", e),
+        }
+    }
+
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        error!("Failed to listen for SIGINT: {}", e);
+        std::future::pending::<()>().await;
+    }
+    "SIGINT"
+}
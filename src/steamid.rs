@@ -0,0 +1,380 @@
+use error_stack::Context;
+use std::str::FromStr;
+
+const ACCOUNT_ID_MASK: u64 = 0xFFFF_FFFF;
+const INSTANCE_SHIFT: u64 = 32;
+const INSTANCE_MASK: u64 = 0xF_FFFF;
+const ACCOUNT_TYPE_SHIFT: u64 = 52;
+const ACCOUNT_TYPE_MASK: u64 = 0xF;
+const UNIVERSE_SHIFT: u64 = 56;
+
+/// Instance used by every individual account.
+const DESKTOP_INSTANCE: u64 = 1;
+
+/// Why a string couldn't be read as a Steam account.
+///
+/// The `Display` output is shown to users, so keep it friendly.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SteamIdError {
+    Invalid,
+    /// A custom profile URL, which needs the Steam Web API to resolve.
+    Vanity(String),
+    Universe(u8),
+    /// Groups, game servers and the like can't own products.
+    AccountType(u8),
+    /// An individual account outside the desktop instance, e.g. a console one.
+    Instance(u32),
+}
+
+impl std::fmt::Display for SteamIdError {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Invalid => fmt.write_str("not a valid SteamID or Steam profile URL"),
+            Self::Vanity(name) => write!(
+                fmt,
+                "`{}` is a custom profile URL, please use the SteamID64 instead",
+                name
+            ),
+            Self::Universe(universe) => {
+                write!(fmt, "universe {} is not a valid Steam universe", universe)
+            }
+            Self::AccountType(kind) => {
+                write!(
+                    fmt,
+                    "account type {} is not an individual Steam account",
+                    kind
+                )
+            }
+            Self::Instance(instance) => {
+                write!(fmt, "instance {} is not a desktop Steam account", instance)
+            }
+        }
+    }
+}
+
+impl Context for SteamIdError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Universe {
+    Public = 1,
+    Beta = 2,
+    Internal = 3,
+    Dev = 4,
+}
+
+impl Universe {
+    fn from_u8(universe: u8) -> Result<Self, SteamIdError> {
+        match universe {
+            1 => Ok(Self::Public),
+            2 => Ok(Self::Beta),
+            3 => Ok(Self::Internal),
+            4 => Ok(Self::Dev),
+            other => Err(SteamIdError::Universe(other)),
+        }
+    }
+}
+
+/// A Steam user account, stored as its SteamID64.
+///
+/// Only individual accounts are accepted, as nothing else can link or buy products.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SteamId(u64);
+
+impl SteamId {
+    /// Account type of individual user accounts.
+    const INDIVIDUAL: u64 = 1;
+
+    pub fn new(universe: Universe, account_id: u32) -> Self {
+        Self(
+            (universe as u64) << UNIVERSE_SHIFT
+                | Self::INDIVIDUAL << ACCOUNT_TYPE_SHIFT
+                | DESKTOP_INSTANCE << INSTANCE_SHIFT
+                | account_id as u64,
+        )
+    }
+
+    /// Validates a raw SteamID64.
+    pub fn from_u64(steam_id: u64) -> Result<Self, SteamIdError> {
+        Universe::from_u8((steam_id >> UNIVERSE_SHIFT) as u8)?;
+
+        let account_type = (steam_id >> ACCOUNT_TYPE_SHIFT) & ACCOUNT_TYPE_MASK;
+        let instance = (steam_id >> INSTANCE_SHIFT) & INSTANCE_MASK;
+        if account_type != Self::INDIVIDUAL {
+            return Err(SteamIdError::AccountType(account_type as u8));
+        }
+        if instance != DESKTOP_INSTANCE {
+            return Err(SteamIdError::Instance(instance as u32));
+        }
+
+        Ok(Self(steam_id))
+    }
+
+    pub fn as_u64(self) -> u64 {
+        self.0
+    }
+
+    pub fn account_id(self) -> u32 {
+        (self.0 & ACCOUNT_ID_MASK) as u32
+    }
+
+    pub fn universe(self) -> Universe {
+        Universe::from_u8((self.0 >> UNIVERSE_SHIFT) as u8).unwrap_or(Universe::Public)
+    }
+
+    /// `STEAM_0:Y:Z`, the form Garry's Mod uses for public accounts.
+    ///
+    /// Newer games write the universe digit as-is, i.e. `STEAM_1:` for public.
+    pub fn steam2(self) -> String {
+        let universe = match self.universe() {
+            Universe::Public => 0,
+            universe => universe as u8,
+        };
+        let account_id = self.account_id();
+        format!("STEAM_{}:{}:{}", universe, account_id & 1, account_id >> 1)
+    }
+
+    /// `[U:1:N]`
+    pub fn steam3(self) -> String {
+        format!("[U:{}:{}]", self.universe() as u8, self.account_id())
+    }
+
+    pub fn profile_url(self) -> String {
+        format!("https://steamcommunity.com/profiles/{}", self.0)
+    }
+
+    /// `STEAM_X:Y:Z`, where universe 0 is read as public since older games report it.
+    fn parse_steam2(input: &str) -> Option<Result<Self, SteamIdError>> {
+        let mut parts = input.strip_prefix("STEAM_")?.split(':');
+        let (Some(universe), Some(y), Some(z), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Some(Err(SteamIdError::Invalid));
+        };
+
+        let (Ok(universe), Ok(y @ 0..=1), Ok(z)) =
+            (universe.parse::<u8>(), y.parse::<u32>(), z.parse::<u32>())
+        else {
+            return Some(Err(SteamIdError::Invalid));
+        };
+
+        let Some(account_id) = z.checked_mul(2).and_then(|z| z.checked_add(y)) else {
+            return Some(Err(SteamIdError::Invalid));
+        };
+
+        let universe = if universe == 0 { 1 } else { universe };
+        Some(Universe::from_u8(universe).map(|universe| Self::new(universe, account_id)))
+    }
+
+    /// `[U:1:N]`, with or without the brackets.
+    fn parse_steam3(input: &str) -> Option<Result<Self, SteamIdError>> {
+        let input = input.strip_prefix('[').unwrap_or(input);
+        let input = input.strip_suffix(']').unwrap_or(input);
+
+        let mut parts = input.split(':');
+        let (Some(kind), Some(universe), Some(account_id), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return None;
+        };
+        let (Ok(universe), Ok(account_id)) = (universe.parse::<u8>(), account_id.parse::<u32>())
+        else {
+            return None;
+        };
+
+        // The letters are case sensitive, anything unlisted isn't a SteamID3 at all.
+        let account_type = match kind {
+            "I" => 0,
+            "U" => Self::INDIVIDUAL as u8,
+            "M" => 2,
+            "G" => 3,
+            "A" => 4,
+            "P" => 5,
+            "C" => 6,
+            "g" => 7,
+            "T" | "L" | "c" => 8,
+            "a" => 10,
+            _ => return None,
+        };
+        if account_type != Self::INDIVIDUAL as u8 {
+            return Some(Err(SteamIdError::AccountType(account_type)));
+        }
+
+        Some(Universe::from_u8(universe).map(|universe| Self::new(universe, account_id)))
+    }
+
+    fn parse_steam64(input: &str) -> Option<Result<Self, SteamIdError>> {
+        input.parse::<u64>().ok().map(Self::from_u64)
+    }
+
+    fn parse_url(input: &str) -> Option<Result<Self, SteamIdError>> {
+        let path = input
+            .trim_start_matches("https://")
+            .trim_start_matches("http://")
+            .trim_start_matches("www.")
+            .strip_prefix("steamcommunity.com/")?;
+
+        let mut segments = path.split(['/', '?', '#']);
+        let kind = segments.next()?;
+        let Some(value) = segments.next().filter(|value| !value.is_empty()) else {
+            return Some(Err(SteamIdError::Invalid));
+        };
+
+        Some(match kind {
+            "profiles" => Self::parse_steam64(value)
+                .or_else(|| Self::parse_steam3(value))
+                .unwrap_or(Err(SteamIdError::Invalid)),
+            "id" => Err(SteamIdError::Vanity(value.to_string())),
+            _ => Err(SteamIdError::Invalid),
+        })
+    }
+}
+
+impl FromStr for SteamId {
+    type Err = SteamIdError;
+
+    /// Reads a SteamID64, SteamID2, SteamID3 or community profile URL.
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let input = input.trim();
+
+        Self::parse_url(input)
+            .or_else(|| Self::parse_steam64(input))
+            .or_else(|| Self::parse_steam2(input))
+            .or_else(|| Self::parse_steam3(input))
+            .unwrap_or(Err(SteamIdError::Invalid))
+    }
+}
+
+impl std::fmt::Display for SteamId {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(fmt, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// STEAM_0:0:11101, [U:1:22202]
+    const EVEN: u64 = 76561197960287930;
+    /// STEAM_0:1:0, [U:1:1]
+    const ODD: u64 = 76561197960265729;
+
+    fn parse(input: &str) -> Result<SteamId, SteamIdError> {
+        input.parse()
+    }
+
+    #[test]
+    fn formats_every_form() {
+        let steam_id = SteamId::from_u64(EVEN).unwrap();
+        assert_eq!(steam_id.account_id(), 22202);
+        assert_eq!(steam_id.universe(), Universe::Public);
+        assert_eq!(steam_id.steam2(), "STEAM_0:0:11101");
+        assert_eq!(steam_id.steam3(), "[U:1:22202]");
+        assert_eq!(steam_id.to_string(), EVEN.to_string());
+        assert_eq!(
+            steam_id.profile_url(),
+            "https://steamcommunity.com/profiles/76561197960287930"
+        );
+
+        let steam_id = SteamId::from_u64(ODD).unwrap();
+        assert_eq!(steam_id.steam2(), "STEAM_0:1:0");
+        assert_eq!(steam_id.steam3(), "[U:1:1]");
+    }
+
+    #[test]
+    fn round_trips_between_forms() {
+        for raw in [EVEN, ODD, SteamId::new(Universe::Public, u32::MAX).as_u64()] {
+            let steam_id = SteamId::from_u64(raw).unwrap();
+
+            for form in [
+                steam_id.to_string(),
+                steam_id.steam2(),
+                steam_id.steam3(),
+                steam_id.profile_url(),
+            ] {
+                assert_eq!(parse(&form), Ok(steam_id), "{}", form);
+            }
+        }
+    }
+
+    #[test]
+    fn new_matches_from_u64() {
+        assert_eq!(
+            SteamId::new(Universe::Public, 22202),
+            SteamId::from_u64(EVEN).unwrap()
+        );
+        assert_eq!(SteamId::new(Universe::Beta, 7).universe(), Universe::Beta);
+    }
+
+    #[test]
+    fn accepts_variations() {
+        let expected = Ok(SteamId::from_u64(EVEN).unwrap());
+
+        assert_eq!(parse("  76561197960287930  "), expected);
+        assert_eq!(parse("STEAM_1:0:11101"), expected);
+        assert_eq!(parse("U:1:22202"), expected);
+        assert_eq!(
+            parse("http://www.steamcommunity.com/profiles/76561197960287930/"),
+            expected
+        );
+        assert_eq!(
+            parse("https://steamcommunity.com/profiles/[U:1:22202]?l=english"),
+            expected
+        );
+    }
+
+    #[test]
+    fn detects_vanity_urls() {
+        assert_eq!(
+            parse("https://steamcommunity.com/id/gabelogannewell/"),
+            Err(SteamIdError::Vanity("gabelogannewell".to_string()))
+        );
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        for input in [
+            "",
+            "a",
+            "u:1:5",
+            "[U:1]",
+            "[U:1:x]",
+            "STEAM_0:2:1",
+            "STEAM_0:1",
+            "STEAM_0:1:4294967295",
+            "https://steamcommunity.com/profiles/",
+            "https://steamcommunity.com/groups/valve",
+            "https://example.com/profiles/76561197960287930",
+        ] {
+            assert_eq!(parse(input), Err(SteamIdError::Invalid), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn rejects_other_universes() {
+        assert_eq!(parse("STEAM_5:0:1"), Err(SteamIdError::Universe(5)));
+        assert_eq!(parse("[U:9:1]"), Err(SteamIdError::Universe(9)));
+        assert_eq!(
+            SteamId::from_u64(EVEN & !(0xFF << UNIVERSE_SHIFT)),
+            Err(SteamIdError::Universe(0))
+        );
+    }
+
+    #[test]
+    fn rejects_other_instances() {
+        let console = EVEN & !(INSTANCE_MASK << INSTANCE_SHIFT) | 2 << INSTANCE_SHIFT;
+        assert_eq!(SteamId::from_u64(console), Err(SteamIdError::Instance(2)));
+        assert_eq!(parse(&console.to_string()), Err(SteamIdError::Instance(2)));
+    }
+
+    #[test]
+    fn rejects_other_account_types() {
+        // Valve's developer group
+        assert_eq!(
+            parse("103582791429521412"),
+            Err(SteamIdError::AccountType(7))
+        );
+        assert_eq!(parse("[g:1:4]"), Err(SteamIdError::AccountType(7)));
+        assert_eq!(parse("[G:1:4]"), Err(SteamIdError::AccountType(3)));
+    }
+}
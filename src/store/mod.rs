@@ -0,0 +1,95 @@
+use chrono::{DateTime, Utc};
+use error_stack::{Context, Result};
+
+mod sqlite;
+
+pub use sqlite::SqliteStore;
+
+#[derive(Debug)]
+pub struct StoreError;
+
+impl std::fmt::Display for StoreError {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt.write_str("Store Error: An error occurred whilst accessing the database")
+    }
+}
+
+impl Context for StoreError {}
+
+/// Something a staff member did, and how it went.
+#[derive(Debug, Clone)]
+pub struct AuditEntry {
+    pub created_at: DateTime<Utc>,
+    /// Discord ID of whoever ran the command.
+    pub actor: u64,
+    /// Discord ID of the member acted upon, if any.
+    pub target: Option<u64>,
+    pub action: String,
+    pub outcome: String,
+    /// Link state of the target before and after, serialised as JSON.
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+/// An audit entry as stored, with its ID.
+#[derive(Debug, Clone)]
+pub struct AuditRecord {
+    pub id: i64,
+    pub entry: AuditEntry,
+}
+
+/// Filters for reading back the audit log, newest entries first.
+#[derive(Debug, Clone, Default)]
+pub struct AuditQuery {
+    pub actor: Option<u64>,
+    pub target: Option<u64>,
+    pub limit: u32,
+}
+
+/// A coupon the bot handed out.
+#[derive(Debug, Clone)]
+pub struct IssuedCoupon {
+    pub issued_at: DateTime<Utc>,
+    pub discord_id: u64,
+    pub gmodstore_id: String,
+    /// Key of the campaign it was claimed from.
+    pub campaign: String,
+    pub product_id: String,
+    pub coupon_id: String,
+    pub code: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// The outcome of a guild wide role sync.
+#[derive(Debug, Clone)]
+pub struct SyncRun {
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub members: u64,
+    pub linked: u64,
+    pub roles_added: u64,
+    pub roles_removed: u64,
+    pub failed: u64,
+}
+
+/// Persistent bot state.
+///
+/// Calls are quick local writes, so they are made straight from async code.
+pub trait Store: Send + Sync {
+    /// Appends to the audit log, returning the entry's ID.
+    fn record_audit(&self, entry: &AuditEntry) -> Result<i64, StoreError>;
+
+    fn audit_entries(&self, query: &AuditQuery) -> Result<Vec<AuditRecord>, StoreError>;
+
+    fn record_coupon(&self, coupon: &IssuedCoupon) -> Result<(), StoreError>;
+
+    /// How many coupons the user has been issued for a campaign, expired ones included.
+    fn coupons_claimed(&self, discord_id: u64, campaign: &str) -> Result<u64, StoreError>;
+
+    fn record_sync_run(&self, run: &SyncRun) -> Result<(), StoreError>;
+
+    fn last_sync_run(&self) -> Result<Option<SyncRun>, StoreError>;
+
+    /// Remembers a webhook delivery, `false` if it was already seen.
+    fn record_delivery(&self, source: &str, id: &str) -> Result<bool, StoreError>;
+}
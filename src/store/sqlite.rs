@@ -0,0 +1,486 @@
+use chrono::Utc;
+use error_stack::{IntoReport, Report, Result, ResultExt};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::sync::{Mutex, MutexGuard};
+
+use super::{AuditEntry, AuditQuery, AuditRecord, IssuedCoupon, Store, StoreError, SyncRun};
+
+/// Schema changes, applied in order. `PRAGMA user_version` records how many have run,
+/// so only ever append to this list.
+const MIGRATIONS: &[&str] = &[
+    // 1: audit log, coupon ledger and sync runs
+    "
+    CREATE TABLE audit_log (
+        id INTEGER PRIMARY KEY,
+        created_at TEXT NOT NULL,
+        actor INTEGER NOT NULL,
+        target INTEGER,
+        action TEXT NOT NULL,
+        outcome TEXT NOT NULL,
+        before TEXT,
+        after TEXT
+    );
+    CREATE INDEX audit_log_target ON audit_log (target, created_at);
+    CREATE INDEX audit_log_actor ON audit_log (actor, created_at);
+
+    CREATE TABLE coupon_ledger (
+        id INTEGER PRIMARY KEY,
+        issued_at TEXT NOT NULL,
+        discord_id INTEGER NOT NULL,
+        gmodstore_id TEXT NOT NULL,
+        campaign TEXT NOT NULL,
+        product_id TEXT NOT NULL,
+        coupon_id TEXT NOT NULL,
+        code TEXT NOT NULL,
+        expires_at TEXT NOT NULL
+    );
+    CREATE INDEX coupon_ledger_user ON coupon_ledger (discord_id, campaign);
+
+    CREATE TABLE sync_runs (
+        id INTEGER PRIMARY KEY,
+        started_at TEXT NOT NULL,
+        finished_at TEXT NOT NULL,
+        members INTEGER NOT NULL,
+        linked INTEGER NOT NULL,
+        roles_added INTEGER NOT NULL,
+        roles_removed INTEGER NOT NULL,
+        failed INTEGER NOT NULL
+    );
+    ",
+    // 2: webhook deliveries, so retried ones are only acted on once
+    "
+    CREATE TABLE webhook_deliveries (
+        source TEXT NOT NULL,
+        id TEXT NOT NULL,
+        received_at TEXT NOT NULL,
+        PRIMARY KEY (source, id)
+    );
+    ",
+];
+
+/// A [`Store`] backed by an SQLite file, or memory when the path is `:memory:`.
+pub struct SqliteStore {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteStore {
+    pub fn open(path: &str) -> Result<Self, StoreError> {
+        let mut connection = Connection::open(path)
+            .into_report()
+            .attach_printable_lazy(|| format!("Failed to open database: {}", path))
+            .change_context(StoreError)?;
+
+        connection
+            .execute_batch("PRAGMA journal_mode = WAL; PRAGMA busy_timeout = 5000;")
+            .into_report()
+            .attach_printable("Failed to configure database")
+            .change_context(StoreError)?;
+
+        Self::migrate(&mut connection)?;
+
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    fn migrate(connection: &mut Connection) -> Result<(), StoreError> {
+        let version: i64 = connection
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .into_report()
+            .attach_printable("Failed to read database version")
+            .change_context(StoreError)?;
+        let version = version as usize;
+
+        if version > MIGRATIONS.len() {
+            return Err(Report::new(StoreError).attach_printable(format!(
+                "Database is at version {} but this build only knows {}",
+                version,
+                MIGRATIONS.len()
+            )));
+        }
+
+        for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            let transaction = connection
+                .transaction()
+                .into_report()
+                .change_context(StoreError)?;
+
+            transaction
+                .execute_batch(migration)
+                .into_report()
+                .attach_printable_lazy(|| format!("Failed to apply migration {}", index + 1))
+                .change_context(StoreError)?;
+            transaction
+                .pragma_update(None, "user_version", (index + 1) as i64)
+                .into_report()
+                .change_context(StoreError)?;
+            transaction
+                .commit()
+                .into_report()
+                .attach_printable_lazy(|| format!("Failed to commit migration {}", index + 1))
+                .change_context(StoreError)?;
+
+            info!("Applied database migration {}", index + 1);
+        }
+
+        Ok(())
+    }
+
+    fn connection(&self) -> MutexGuard<'_, Connection> {
+        // A panic mid-query can't leave SQLite inconsistent, so poisoning is ignored.
+        self.connection
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Store for SqliteStore {
+    fn record_audit(&self, entry: &AuditEntry) -> Result<i64, StoreError> {
+        let connection = self.connection();
+        connection
+            .execute(
+                "INSERT INTO audit_log (created_at, actor, target, action, outcome, before, after)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    entry.created_at,
+                    entry.actor as i64,
+                    entry.target.map(|target| target as i64),
+                    entry.action,
+                    entry.outcome,
+                    entry.before,
+                    entry.after,
+                ],
+            )
+            .into_report()
+            .attach_printable("Failed to record audit entry")
+            .change_context(StoreError)?;
+
+        Ok(connection.last_insert_rowid())
+    }
+
+    fn audit_entries(&self, query: &AuditQuery) -> Result<Vec<AuditRecord>, StoreError> {
+        let connection = self.connection();
+        let mut statement = connection
+            .prepare(
+                "SELECT id, created_at, actor, target, action, outcome, before, after
+                 FROM audit_log
+                 WHERE (?1 IS NULL OR actor = ?1) AND (?2 IS NULL OR target = ?2)
+                 ORDER BY id DESC LIMIT ?3",
+            )
+            .into_report()
+            .change_context(StoreError)?;
+
+        let records = statement
+            .query_map(
+                params![
+                    query.actor.map(|actor| actor as i64),
+                    query.target.map(|target| target as i64),
+                    query.limit,
+                ],
+                |row| {
+                    Ok(AuditRecord {
+                        id: row.get(0)?,
+                        entry: AuditEntry {
+                            created_at: row.get(1)?,
+                            actor: row.get::<_, i64>(2)? as u64,
+                            target: row.get::<_, Option<i64>>(3)?.map(|target| target as u64),
+                            action: row.get(4)?,
+                            outcome: row.get(5)?,
+                            before: row.get(6)?,
+                            after: row.get(7)?,
+                        },
+                    })
+                },
+            )
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+            .into_report()
+            .attach_printable("Failed to read audit log")
+            .change_context(StoreError)?;
+
+        Ok(records)
+    }
+
+    fn record_coupon(&self, coupon: &IssuedCoupon) -> Result<(), StoreError> {
+        self.connection()
+            .execute(
+                "INSERT INTO coupon_ledger
+                 (issued_at, discord_id, gmodstore_id, campaign, product_id, coupon_id, code, expires_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    coupon.issued_at,
+                    coupon.discord_id as i64,
+                    coupon.gmodstore_id,
+                    coupon.campaign,
+                    coupon.product_id,
+                    coupon.coupon_id,
+                    coupon.code,
+                    coupon.expires_at,
+                ],
+            )
+            .into_report()
+            .attach_printable("Failed to record issued coupon")
+            .change_context(StoreError)?;
+
+        Ok(())
+    }
+
+    fn coupons_claimed(&self, discord_id: u64, campaign: &str) -> Result<u64, StoreError> {
+        let claimed: i64 = self
+            .connection()
+            .query_row(
+                "SELECT COUNT(*) FROM coupon_ledger WHERE discord_id = ?1 AND campaign = ?2",
+                params![discord_id as i64, campaign],
+                |row| row.get(0),
+            )
+            .into_report()
+            .attach_printable("Failed to count claimed coupons")
+            .change_context(StoreError)?;
+
+        Ok(claimed as u64)
+    }
+
+    fn record_sync_run(&self, run: &SyncRun) -> Result<(), StoreError> {
+        self.connection()
+            .execute(
+                "INSERT INTO sync_runs
+                 (started_at, finished_at, members, linked, roles_added, roles_removed, failed)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    run.started_at,
+                    run.finished_at,
+                    run.members as i64,
+                    run.linked as i64,
+                    run.roles_added as i64,
+                    run.roles_removed as i64,
+                    run.failed as i64,
+                ],
+            )
+            .into_report()
+            .attach_printable("Failed to record sync run")
+            .change_context(StoreError)?;
+
+        Ok(())
+    }
+
+    fn last_sync_run(&self) -> Result<Option<SyncRun>, StoreError> {
+        self.connection()
+            .query_row(
+                "SELECT started_at, finished_at, members, linked, roles_added, roles_removed, failed
+                 FROM sync_runs ORDER BY id DESC LIMIT 1",
+                [],
+                |row| {
+                    Ok(SyncRun {
+                        started_at: row.get(0)?,
+                        finished_at: row.get(1)?,
+                        members: row.get::<_, i64>(2)? as u64,
+                        linked: row.get::<_, i64>(3)? as u64,
+                        roles_added: row.get::<_, i64>(4)? as u64,
+                        roles_removed: row.get::<_, i64>(5)? as u64,
+                        failed: row.get::<_, i64>(6)? as u64,
+                    })
+                },
+            )
+            .optional()
+            .into_report()
+            .attach_printable("Failed to read last sync run")
+            .change_context(StoreError)
+    }
+
+    fn record_delivery(&self, source: &str, id: &str) -> Result<bool, StoreError> {
+        let inserted = self
+            .connection()
+            .execute(
+                "INSERT OR IGNORE INTO webhook_deliveries (source, id, received_at)
+                 VALUES (?1, ?2, ?3)",
+                params![source, id, Utc::now()],
+            )
+            .into_report()
+            .attach_printable_lazy(|| format!("Failed to record {} delivery {}", source, id))
+            .change_context(StoreError)?;
+
+        Ok(inserted == 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn store() -> SqliteStore {
+        SqliteStore::open(":memory:").unwrap()
+    }
+
+    fn audit(actor: u64, target: Option<u64>, action: &str) -> AuditEntry {
+        AuditEntry {
+            created_at: Utc::now(),
+            actor,
+            target,
+            action: action.to_string(),
+            outcome: "ok".to_string(),
+            before: None,
+            after: None,
+        }
+    }
+
+    fn coupon(discord_id: u64, campaign: &str) -> IssuedCoupon {
+        let now = Utc::now();
+        IssuedCoupon {
+            issued_at: now,
+            discord_id,
+            gmodstore_id: "abc".to_string(),
+            campaign: campaign.to_string(),
+            product_id: "product".to_string(),
+            coupon_id: "coupon".to_string(),
+            code: format!("{}-CODE", campaign.to_uppercase()),
+            expires_at: now + Duration::days(7),
+        }
+    }
+
+    fn sync_run(members: u64) -> SyncRun {
+        let now = Utc::now();
+        SyncRun {
+            started_at: now,
+            finished_at: now,
+            members,
+            linked: 2,
+            roles_added: 3,
+            roles_removed: 4,
+            failed: 5,
+        }
+    }
+
+    #[test]
+    fn migrates_to_latest() {
+        let store = store();
+        let mut connection = store.connection();
+        let version: i64 = connection
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(version as usize, MIGRATIONS.len());
+
+        // Running again is a no-op.
+        SqliteStore::migrate(&mut connection).unwrap();
+    }
+
+    #[test]
+    fn refuses_newer_databases() {
+        let store = store();
+        let mut connection = store.connection();
+        connection
+            .pragma_update(None, "user_version", MIGRATIONS.len() as i64 + 1)
+            .unwrap();
+
+        assert!(SqliteStore::migrate(&mut connection).is_err());
+    }
+
+    #[test]
+    fn filters_audit_entries() {
+        let store = store();
+        let first = store.record_audit(&audit(1, Some(10), "link")).unwrap();
+        store.record_audit(&audit(1, None, "sync")).unwrap();
+        store.record_audit(&audit(2, Some(10), "link")).unwrap();
+        // Larger than an i64, to check IDs survive the round trip.
+        let big = u64::MAX - 1;
+        store.record_audit(&audit(big, Some(big), "link")).unwrap();
+
+        let ids = |query: AuditQuery| -> Vec<u64> {
+            store
+                .audit_entries(&query)
+                .unwrap()
+                .into_iter()
+                .map(|record| record.entry.actor)
+                .collect()
+        };
+
+        assert_eq!(
+            ids(AuditQuery {
+                limit: 10,
+                ..Default::default()
+            }),
+            [big, 2, 1, 1]
+        );
+        assert_eq!(
+            ids(AuditQuery {
+                actor: Some(1),
+                limit: 10,
+                ..Default::default()
+            }),
+            [1, 1]
+        );
+        assert_eq!(
+            ids(AuditQuery {
+                target: Some(10),
+                limit: 10,
+                ..Default::default()
+            }),
+            [2, 1]
+        );
+        assert_eq!(
+            ids(AuditQuery {
+                actor: Some(1),
+                target: Some(10),
+                limit: 10,
+            }),
+            [1]
+        );
+        assert_eq!(
+            ids(AuditQuery {
+                target: Some(big),
+                limit: 10,
+                ..Default::default()
+            }),
+            [big]
+        );
+
+        let records = store
+            .audit_entries(&AuditQuery {
+                limit: 2,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert!(records.iter().all(|record| record.id > first));
+    }
+
+    #[test]
+    fn counts_claimed_coupons() {
+        let store = store();
+        assert_eq!(store.coupons_claimed(1, "spring").unwrap(), 0);
+
+        store.record_coupon(&coupon(1, "spring")).unwrap();
+        store.record_coupon(&coupon(1, "spring")).unwrap();
+        store.record_coupon(&coupon(1, "autumn")).unwrap();
+        store.record_coupon(&coupon(2, "spring")).unwrap();
+
+        assert_eq!(store.coupons_claimed(1, "spring").unwrap(), 2);
+        assert_eq!(store.coupons_claimed(1, "autumn").unwrap(), 1);
+        assert_eq!(store.coupons_claimed(2, "spring").unwrap(), 1);
+        assert_eq!(store.coupons_claimed(2, "autumn").unwrap(), 0);
+    }
+
+    #[test]
+    fn records_deliveries_once() {
+        let store = store();
+        assert!(store.record_delivery("gmodstore", "a").unwrap());
+        assert!(!store.record_delivery("gmodstore", "a").unwrap());
+        assert!(store.record_delivery("gmodstore", "b").unwrap());
+        assert!(store.record_delivery("linksite", "a").unwrap());
+    }
+
+    #[test]
+    fn returns_last_sync_run() {
+        let store = store();
+        assert!(store.last_sync_run().unwrap().is_none());
+
+        store.record_sync_run(&sync_run(1)).unwrap();
+        store.record_sync_run(&sync_run(2)).unwrap();
+
+        let run = store.last_sync_run().unwrap().unwrap();
+        assert_eq!(run.members, 2);
+        assert_eq!(
+            (run.linked, run.roles_added, run.roles_removed, run.failed),
+            (2, 3, 4, 5)
+        );
+    }
+}
@@ -0,0 +1,289 @@
+use chrono::Utc;
+use error_stack::{Context, IntoReport, Report, Result, ResultExt};
+use futures::{StreamExt, TryStreamExt};
+use serenity::{
+    http::{Http, StatusCode},
+    model::{
+        guild::Member,
+        id::{GuildId, UserId},
+    },
+};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{task::JoinHandle, time::MissedTickBehavior};
+
+use crate::{
+    config::Config,
+    http::{ApiError, CacheStats, HttpClient},
+    shutdown::Shutdown,
+    store::{Store, SyncRun},
+};
+
+#[derive(Debug)]
+pub struct RoleSyncError;
+
+impl std::fmt::Display for RoleSyncError {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt.write_str("Sync Error: An error occurred whilst synchronising guild roles")
+    }
+}
+
+impl Context for RoleSyncError {}
+
+#[derive(Debug, Default)]
+pub struct SyncSummary {
+    pub members: usize,
+    pub linked: usize,
+    pub roles_added: usize,
+    pub roles_removed: usize,
+    pub failed: usize,
+    pub elapsed: Duration,
+    pub user_cache: CacheStats,
+    pub purchase_cache: CacheStats,
+}
+
+/// Everything a sync run needs, cheap to clone into the background task.
+#[derive(Clone)]
+pub struct RoleSync {
+    pub discord: Arc<Http>,
+    pub http: Arc<HttpClient>,
+    pub config: Arc<Config>,
+    pub store: Arc<dyn Store>,
+    pub shutdown: Arc<Shutdown>,
+    pub guild_id: GuildId,
+}
+
+impl RoleSync {
+    /// How long until the next run is due, counting from the last recorded run so
+    /// restarts don't push it back.
+    fn first_delay(&self, period: Duration) -> Duration {
+        let last = match self.store.last_sync_run() {
+            Ok(last) => last,
+            Err(e) => {
+                warn!("{:#?}", e);
+                None
+            }
+        };
+
+        let Some(last) = last else {
+            return period;
+        };
+
+        let since = (Utc::now() - last.finished_at).to_std().unwrap_or_default();
+        period.saturating_sub(since)
+    }
+
+    /// Spawns the periodic sync, the first run happens one interval after the last
+    /// recorded one. It stops scheduling runs once shutdown begins.
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let period = Duration::from_secs(self.config.sync.interval);
+            let first = self.first_delay(period);
+            debug!("First role sync in {}s", first.as_secs());
+
+            let mut interval =
+                tokio::time::interval_at(tokio::time::Instant::now() + first, period);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = self.shutdown.closed() => break,
+                }
+                // Held for the whole run, so shutdown waits for it to finish.
+                let Some(_activity) = self.shutdown.track() else {
+                    break;
+                };
+
+                info!("Starting scheduled role sync for guild {}", self.guild_id);
+                match self.run().await {
+                    Ok(summary) => {
+                        info!(
+                            "Role sync finished: {} members, +{} -{} roles, {} failed",
+                            summary.members,
+                            summary.roles_added,
+                            summary.roles_removed,
+                            summary.failed
+                        );
+
+                        if let Err(e) = self.post_summary(&summary).await {
+                            error!("{:#?}", e);
+                        }
+                    }
+                    Err(e) => {
+                        sentry::capture_error(&e.as_error());
+                        error!("{:#?}", e);
+                    }
+                }
+            }
+        })
+    }
+
+    pub async fn run(&self) -> Result<SyncSummary, RoleSyncError> {
+        let started = Instant::now();
+        let started_at = Utc::now();
+
+        let members: Vec<Member> = self
+            .guild_id
+            .members_iter(&*self.discord)
+            .try_collect()
+            .await
+            .into_report()
+            .attach_printable("Failed to fetch guild members")
+            .change_context(RoleSyncError)?;
+
+        let mut summary = SyncSummary::default();
+
+        let members: Vec<_> = members.into_iter().filter(|m| !m.user.bot).collect();
+        let total = members.len();
+
+        // Members already in flight finish, but none are started once shutdown begins.
+        let mut results = futures::stream::iter(members)
+            .take_while(|_| futures::future::ready(!self.shutdown.is_closing()))
+            .map(|member| self.sync_member(member))
+            .buffer_unordered(self.config.sync.concurrency);
+
+        while let Some(result) = results.next().await {
+            summary.members += 1;
+
+            match result {
+                Ok((linked, diff)) => {
+                    if linked {
+                        summary.linked += 1;
+                    }
+                    summary.roles_added += diff.added.len();
+                    summary.roles_removed += diff.removed.len();
+                }
+                // Every other member would fail the same way, so stop here.
+                Err(e) if matches!(e.downcast_ref::<ApiError>(), Some(ApiError::Unauthorized)) => {
+                    return Err(e.attach_printable(
+                        "Aborting role sync, the link API rejected our credentials",
+                    ));
+                }
+                Err(e) => {
+                    summary.failed += 1;
+                    warn!("{:#?}", e);
+                }
+            }
+        }
+
+        if summary.members < total {
+            warn!(
+                "Role sync interrupted by shutdown after {} of {} members",
+                summary.members, total
+            );
+        }
+
+        summary.elapsed = started.elapsed();
+        summary.user_cache = self.http.link_client.cache.user_stats();
+        summary.purchase_cache = self.http.link_client.cache.purchase_stats();
+
+        let run = SyncRun {
+            started_at,
+            finished_at: Utc::now(),
+            members: summary.members as u64,
+            linked: summary.linked as u64,
+            roles_added: summary.roles_added as u64,
+            roles_removed: summary.roles_removed as u64,
+            failed: summary.failed as u64,
+        };
+        if let Err(e) = self.store.record_sync_run(&run) {
+            warn!("{:#?}", e);
+        }
+
+        Ok(summary)
+    }
+
+    /// Reconciles one member straight away, `None` when they aren't in the guild.
+    pub async fn sync_user(
+        &self,
+        user_id: UserId,
+    ) -> Result<Option<(bool, crate::roles::RoleDiff)>, RoleSyncError> {
+        let member = match self.guild_id.member(&*self.discord, user_id).await {
+            Ok(member) => member,
+            Err(serenity::Error::Http(e)) if e.status_code() == Some(StatusCode::NOT_FOUND) => {
+                return Ok(None)
+            }
+            Err(e) => {
+                return Err(Report::new(e)
+                    .attach_printable(format!("Failed to fetch member {}", user_id))
+                    .change_context(RoleSyncError))
+            }
+        };
+
+        self.sync_member(member).await.map(Some)
+    }
+
+    async fn sync_member(
+        &self,
+        mut member: Member,
+    ) -> Result<(bool, crate::roles::RoleDiff), RoleSyncError> {
+        let user = self
+            .http
+            .link_client
+            .get_user_by_discord(member.user.id.0)
+            .await
+            .attach_printable_lazy(|| format!("Failed to look up {}", member.user.tag()))
+            .change_context(RoleSyncError)?;
+
+        let purchases = match &user {
+            Some(user) => match user.get_purchases().await {
+                Ok(purchases) => Some(purchases),
+                // Unlinked since the lookup above.
+                Err(e) if matches!(e.downcast_ref::<ApiError>(), Some(ApiError::NotFound)) => None,
+                Err(e) => return Err(e.change_context(RoleSyncError)),
+            },
+            None => None,
+        };
+        let linked = purchases.is_some();
+
+        let diff =
+            crate::roles::reconcile(&self.discord, &mut member, &self.config, purchases.as_ref())
+                .await
+                .change_context(RoleSyncError)?;
+
+        Ok((linked, diff))
+    }
+
+    async fn post_summary(&self, summary: &SyncSummary) -> Result<(), RoleSyncError> {
+        let Some(channel) = self.config.sync.log_channel else {
+            return Ok(());
+        };
+
+        channel
+            .send_message(&*self.discord, |message| {
+                message.add_embed(|embed| {
+                    embed
+                        .title("Role Sync")
+                        .field("Members", summary.members, true)
+                        .field("Linked", summary.linked, true)
+                        .field("Failed", summary.failed, true)
+                        .field("Roles Added", summary.roles_added, true)
+                        .field("Roles Removed", summary.roles_removed, true)
+                        .field(
+                            "Duration",
+                            format!("{:.1}s", summary.elapsed.as_secs_f32()),
+                            true,
+                        )
+                        .field(
+                            "Cache Hit Rate",
+                            format!(
+                                "Users {:.0}%, Purchases {:.0}%",
+                                summary.user_cache.hit_rate() * 100.0,
+                                summary.purchase_cache.hit_rate() * 100.0
+                            ),
+                            false,
+                        )
+                        .colour(serenity::utils::Colour::from(0xBF8AE0))
+                })
+            })
+            .await
+            .into_report()
+            .attach_printable("Failed to post role sync summary")
+            .change_context(RoleSyncError)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,108 @@
+use serenity::model::{id::ChannelId, mention::Mention, user::User};
+
+/// Values available to message templates.
+///
+/// Supported placeholders are `{user}`, `{username}`, `{member_count}`,
+/// `{linked}` and `{channel:<id>}`. Anything else is left untouched.
+pub struct TemplateContext<'a> {
+    pub user: &'a User,
+    pub member_count: Option<u64>,
+    /// `None` when the link API couldn't be reached.
+    pub linked: Option<bool>,
+}
+
+impl TemplateContext<'_> {
+    fn resolve(&self, placeholder: &str) -> Option<String> {
+        match placeholder {
+            "user" => Some(Mention::User(self.user.id).to_string()),
+            "username" => Some(self.user.name.clone()),
+            "member_count" => Some(
+                self.member_count
+                    .map_or_else(|| "?".to_string(), |count| count.to_string()),
+            ),
+            "linked" => Some(
+                match self.linked {
+                    Some(true) => "Linked",
+                    Some(false) => "Not linked",
+                    None => "Unknown",
+                }
+                .to_string(),
+            ),
+            _ => {
+                let id = placeholder.strip_prefix("channel:")?.parse::<u64>().ok()?;
+                Some(Mention::Channel(ChannelId(id)).to_string())
+            }
+        }
+    }
+
+    pub fn render(&self, template: &str) -> String {
+        let mut output = String::with_capacity(template.len());
+        let mut rest = template;
+
+        while let Some(start) = rest.find('{') {
+            output.push_str(&rest[..start]);
+            rest = &rest[start..];
+
+            let Some(end) = rest.find('}') else {
+                break;
+            };
+
+            // A stray `{` is plain text, the placeholder starts at the last one before `}`.
+            let start = rest[..end].rfind('{').unwrap_or_default();
+            output.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let end = end - start;
+
+            match self.resolve(&rest[1..end]) {
+                Some(value) => output.push_str(&value),
+                None => output.push_str(&rest[..=end]),
+            }
+            rest = &rest[end + 1..];
+        }
+
+        output.push_str(rest);
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(template: &str) -> String {
+        let mut user = User::default();
+        user.id = 1.into();
+        user.name = "Ley".to_string();
+        let ctx = TemplateContext {
+            user: &user,
+            member_count: Some(42),
+            linked: None,
+        };
+        ctx.render(template)
+    }
+
+    #[test]
+    fn replaces_placeholders() {
+        assert_eq!(
+            render("Hi {user} ({username}), member {member_count}: {linked}"),
+            "Hi <@1> (Ley), member 42: Unknown"
+        );
+        assert_eq!(render("Read {channel:5}"), "Read <#5>");
+    }
+
+    #[test]
+    fn leaves_unknown_text_alone() {
+        assert_eq!(
+            render("{nope} {channel:x} {user"),
+            "{nope} {channel:x} {user"
+        );
+        assert_eq!(render("a } b"), "a } b");
+    }
+
+    #[test]
+    fn skips_unmatched_braces() {
+        assert_eq!(render("{a {user}"), "{a <@1>");
+        assert_eq!(render("{{username}}"), "{Ley}");
+        assert_eq!(render("{ {x {member_count}!"), "{ {x 42!");
+    }
+}